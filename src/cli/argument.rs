@@ -1,6 +1,7 @@
 use clap::builder::styling::AnsiColor;
 use clap::builder::{PossibleValue, Styles};
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use fluxy::{geolookup::GeoRadius, proxy::score::ScoreWeights};
 
 fn get_styles() -> Styles {
     Styles::styled()
@@ -17,14 +18,107 @@ fn get_styles() -> Styles {
     styles=get_styles()
 )]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// List of ISO country codes to filter proxies by location.
     #[arg(short, long, num_args(1..))]
     pub countries: Vec<String>,
 
+    /// List of ISO country codes to exclude.
+    #[arg(long, num_args(1..))]
+    pub exclude_countries: Vec<String>,
+
+    /// List of continent codes to filter proxies by location (AF, AN, AS, EU, NA, OC, SA).
+    #[arg(long, num_args(1..))]
+    pub continents: Vec<String>,
+
+    /// List of continent codes to exclude.
+    #[arg(long, num_args(1..))]
+    pub exclude_continents: Vec<String>,
+
+    /// List of region ISO codes to filter proxies by location, e.g. `CA` or `US-CA`.
+    #[arg(long, num_args(1..))]
+    pub regions: Vec<String>,
+
+    /// List of region ISO codes to exclude.
+    #[arg(long, num_args(1..))]
+    pub exclude_regions: Vec<String>,
+
+    /// List of city names to filter proxies by location.
+    #[arg(long, num_args(1..))]
+    pub cities: Vec<String>,
+
+    /// List of city names to exclude.
+    #[arg(long, num_args(1..))]
+    pub exclude_cities: Vec<String>,
+
+    /// Look up the autonomous system number and organisation of each proxy.
+    #[arg(long)]
+    pub asn: bool,
+
+    /// List of autonomous system numbers to filter proxies by network.
+    #[arg(long, num_args(1..))]
+    pub asns: Vec<u32>,
+
+    /// List of autonomous system numbers to exclude, e.g. hosting providers.
+    #[arg(long, num_args(1..))]
+    pub exclude_asns: Vec<u32>,
+
+    /// Keep only proxies within a distance of a point, given as `<LAT>,<LON>,<KM>`.
+    #[arg(long, value_name = "LAT,LON,KM", value_parser = parse_radius)]
+    pub within: Option<GeoRadius>,
+
+    /// Keep only proxies located in the European Union.
+    #[arg(long)]
+    pub eu_only: bool,
+
+    /// Apply the location filters to the address traffic exits from, as seen while
+    /// validating, instead of the address the proxy listens on. Proxies whose judge does not
+    /// report the exit address are filtered by the address they listen on.
+    #[arg(long, requires("types"))]
+    pub geo_exit: bool,
+
+    /// Path or URL of the GeoLite2-City database; a file given by path is never refreshed.
+    #[arg(long, value_name = "PATH|URL")]
+    pub geo_db: Option<String>,
+
+    /// Path or URL of the GeoLite2-ASN database; a file given by path is never refreshed.
+    #[arg(long, value_name = "PATH|URL")]
+    pub geo_asn_db: Option<String>,
+
+    /// Age in days after which the GeoIP databases are downloaded again; 0 never refreshes.
+    #[arg(long, default_value = "30", value_name = "DAYS")]
+    pub geo_max_age: u64,
+
     /// Maximum number of concurrent proxy checks.
     #[arg(short, long, default_value = "500", value_parser = clap::value_parser!(u64).range(1..))]
     pub max_connections: u64,
 
+    /// Which proxies are considered duplicates; only the first sighting of each is kept.
+    #[arg(
+        long,
+        default_value = "ip-port",
+        value_parser([
+            PossibleValue::new("none"),
+            PossibleValue::new("ip"),
+            PossibleValue::new("ip-port"),
+        ])
+    )]
+    pub dedup: String,
+
+    /// Merge the protocols of duplicate proxies; waits until every source is gathered.
+    #[arg(long)]
+    pub merge_duplicates: bool,
+
+    /// Skip proxies listed in the output of a previous run.
+    #[arg(long, value_name = "FILE")]
+    pub skip_seen: Option<std::path::PathBuf>,
+
+    /// Maximum number of proxies queued between gathering, validation and output.
+    #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(1..))]
+    pub queue_size: u64,
+
     /// Timeout duration in seconds before giving up.
     #[arg(long, default_value = "3", value_parser = clap::value_parser!(u64).range(1..))]
     pub timeout: u64,
@@ -61,10 +155,39 @@ pub struct Cli {
     #[arg(short, long, default_value = "0")]
     pub limit: usize,
 
+    /// Sort the results; they are buffered first, see `--buffer`.
+    #[arg(
+        long,
+        value_name = "KEY",
+        value_parser([
+            PossibleValue::new("score").help("Highest score first"),
+            PossibleValue::new("latency").help("Fastest first"),
+            PossibleValue::new("country").help("By country code, then fastest first"),
+        ])
+    )]
+    pub sort: Option<String>,
+
+    /// Number of results sorted together before being output; 0 waits for all of them.
+    #[arg(long, default_value = "0", value_name = "N", requires("sort"))]
+    pub buffer: usize,
+
+    /// Weights of the score components, e.g. `latency=0.5,bandwidth=0`. Components are
+    /// latency, anonymity, success_rate, bandwidth and protocols.
+    #[arg(long, value_name = "WEIGHTS", requires("sort"))]
+    pub score_weights: Option<ScoreWeights>,
+
     /// File path to save the retrieved proxies. If not provided, output will go to the console.
     #[arg(short, long)]
     pub output_file: Option<std::path::PathBuf>,
 
+    /// Only use provider sources cached by previous runs; never touch the network.
+    #[arg(long, conflicts_with("no_cache"))]
+    pub offline: bool,
+
+    /// Always download provider sources in full without reading or writing the cache.
+    #[arg(long)]
+    pub no_cache: bool,
+
     /// Proxy types (protocols) to validate. [possible values: HTTP{:Transparent,
     /// :Anonymous,:Elite}, HTTPS, SOCKS4, SOCKS5, CONNECT:<port>]
     #[arg(
@@ -75,9 +198,18 @@ pub struct Cli {
     )]
     pub types: Vec<String>,
 
-    /// File path containing proxies. Overrides providers if specified.
-    #[arg(long, help_heading = "Validate", requires("types"))]
-    pub file: Option<std::path::PathBuf>,
+    /// Files, directories or glob patterns containing proxies, `-` reads from stdin.
+    /// Overrides providers unless `--providers` is given.
+    #[arg(long = "file", num_args(1..))]
+    pub files: Vec<std::path::PathBuf>,
+
+    /// Also fetch proxies from the built-in providers when `--file` is given.
+    #[arg(long, requires("files"))]
+    pub providers: bool,
+
+    /// Public IPv4 and/or IPv6 address of this machine, used instead of detecting it.
+    #[arg(long, num_args(1..), help_heading = "Validate")]
+    pub public_ip: Vec<std::net::IpAddr>,
 
     /// Maximum number of attempts to validate a proxy.
     #[arg(
@@ -87,4 +219,181 @@ pub struct Cli {
         requires("types")
     )]
     pub max_attempts: usize,
+
+    /// Number of checks run on each proxy; proxies passing the first one are checked again to
+    /// catch those that stop working.
+    #[arg(
+        long,
+        default_value = "1",
+        value_name = "N",
+        help_heading = "Validate",
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires("types")
+    )]
+    pub checks: u64,
+
+    /// Pause between two checks of the same proxy, in milliseconds.
+    #[arg(
+        long,
+        default_value = "0",
+        value_name = "MS",
+        help_heading = "Validate",
+        requires("checks")
+    )]
+    pub check_interval: u64,
+
+    /// Minimum share of the checks a proxy must pass, between 0 and 1.
+    #[arg(
+        long,
+        default_value = "1",
+        value_name = "RATE",
+        help_heading = "Validate",
+        value_parser = parse_rate,
+        requires("checks")
+    )]
+    pub min_success_rate: f64,
+
+    /// Self-hosted judge of HTTPS checks, used instead of the public ones.
+    #[arg(long, value_name = "URL", help_heading = "Validate", requires("types"))]
+    pub https_judge: Option<String>,
+
+    /// PEM or DER certificate the HTTPS judge must present, e.g. a self-signed one. Without
+    /// it, the certificate chain of the judge is verified, and if rejected, compared with
+    /// the one the judge presents on a direct connection.
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Validate",
+        requires("https_judge")
+    )]
+    pub https_judge_cert: Option<std::path::PathBuf>,
+
+    /// Drop HTTPS proxies replacing the certificate of the judge, i.e. intercepting TLS.
+    #[arg(long, help_heading = "Validate", requires("types"))]
+    pub drop_intercepted: bool,
+
+    /// Measure the download throughput of each working proxy.
+    #[arg(long, help_heading = "Validate", requires("types"))]
+    pub bandwidth: bool,
+
+    /// URL of the payload downloaded by the bandwidth test; `{bytes}` is replaced by
+    /// `--bandwidth-bytes`. It is requested over HTTP or HTTPS to match the proxy type.
+    #[arg(
+        long,
+        value_name = "URL",
+        help_heading = "Validate",
+        default_value = fluxy::validator::BANDWIDTH_URL
+    )]
+    pub bandwidth_url: String,
+
+    /// Number of bytes downloaded by the bandwidth test.
+    #[arg(
+        long,
+        value_name = "BYTES",
+        help_heading = "Validate",
+        default_value = "1000000"
+    )]
+    pub bandwidth_bytes: usize,
+
+    /// Drop proxies slower than this many kilobytes per second; implies `--bandwidth`.
+    #[arg(
+        long,
+        value_name = "KB/S",
+        help_heading = "Validate",
+        requires("types")
+    )]
+    pub min_bandwidth: Option<f64>,
+
+    /// Fetch a known page through each working HTTP proxy and record whether the proxy
+    /// modified its body or headers, e.g. by injecting ads or scripts.
+    #[arg(long, help_heading = "Validate", requires("types"))]
+    pub tamper_check: bool,
+
+    /// URL of the page fetched by the tampering check; it must be served over plain HTTP
+    /// with the same content for every client.
+    #[arg(
+        long,
+        value_name = "URL",
+        help_heading = "Validate",
+        default_value = fluxy::validator::TAMPER_URL
+    )]
+    pub tamper_url: String,
+
+    /// Hex encoded SHA-256 the page of the tampering check must have when fetched without a
+    /// proxy.
+    #[arg(long, value_name = "HEX", help_heading = "Validate")]
+    pub tamper_sha256: Option<String>,
+
+    /// Drop HTTP proxies modifying the page of the tampering check; implies `--tamper-check`.
+    #[arg(long, help_heading = "Validate", requires("types"))]
+    pub drop_tampered: bool,
+
+    /// Also output proxies failing validation, with the reason and stage of each failed check.
+    /// Skipped by `--format text` and not counted by `--limit`; best used with `--format json`.
+    #[arg(long, help_heading = "Validate", requires("types"))]
+    pub report_failures: bool,
+}
+
+/// Additional commands besides finding proxies.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// List the provider sources, or fetch them and report their health.
+    Sources {
+        /// Fetch every source and print its status, size, duration and proxy counts.
+        #[arg(long)]
+        report: bool,
+
+        /// Also validate the fetched proxies (HTTP unless `--types` is given) and report the
+        /// success rate of each source.
+        #[arg(long, requires = "report")]
+        validate: bool,
+    },
+    /// Manage the GeoIP databases.
+    Geo {
+        #[command(subcommand)]
+        command: GeoCommand,
+    },
+}
+
+/// Commands managing the GeoIP databases.
+#[derive(Subcommand, Debug, Clone)]
+pub enum GeoCommand {
+    /// Download the latest databases, replacing the current ones once validated.
+    Update {
+        /// Also update the GeoLite2-ASN database.
+        #[arg(long)]
+        asn: bool,
+    },
+}
+
+/// Parses a share between 0 and 1.
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.trim().parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        Ok(_) => Err("expected a value between 0 and 1".into()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parses a `<LAT>,<LON>,<KM>` area.
+fn parse_radius(value: &str) -> Result<GeoRadius, String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    match parts[..] {
+        [latitude, longitude, km]
+            if (-90.0..=90.0).contains(&latitude)
+                && (-180.0..=180.0).contains(&longitude)
+                && km >= 0.0 =>
+        {
+            Ok(GeoRadius {
+                latitude,
+                longitude,
+                km,
+            })
+        }
+        _ => Err("expected <LAT>,<LON>,<KM> with valid coordinates".to_string()),
+    }
 }
@@ -1,6 +1,12 @@
-use std::{fs::File, io::Write};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::Write,
+    path::PathBuf,
+    time::Duration,
+};
 
-use argument::Cli;
+use argument::{Cli, Command, GeoCommand};
 use clap::{
     error::{ContextKind, ContextValue, ErrorKind},
     CommandFactory, Parser,
@@ -8,9 +14,16 @@ use clap::{
 #[cfg(feature = "log")]
 use fluxy::initialize_logging;
 use fluxy::{
-    proxy::models::{Anonymity, Protocol, Proxy},
+    fetcher::{self, DedupMode, ProxyFetcher, SourceStats},
+    geolookup::{self, Database, GeoFilter},
+    proxy::{
+        models::{Anonymity, Protocol, Proxy},
+        score::{self, SortKey},
+    },
+    resolver::IpDetector,
     ProxySource, ProxyValidator,
 };
+use serde::Serialize;
 use tokio::runtime;
 
 mod argument;
@@ -71,10 +84,45 @@ fn convert_protocols(types: &[String]) -> Vec<Protocol> {
         .collect()
 }
 
+/// Buffers the results in batches of `--buffer` proxies, ranks and sorts each batch.
+///
+/// Failure reports are not ranked; they follow the working proxies of their batch.
+fn sorted<I>(mut source: I, options: &Cli) -> Box<dyn Iterator<Item = Proxy>>
+where
+    I: Iterator<Item = Proxy> + 'static,
+{
+    let key = match options.sort.as_deref() {
+        Some("score") => SortKey::Score,
+        Some("latency") => SortKey::Latency,
+        Some("country") => SortKey::Country,
+        _ => return Box::new(source),
+    };
+    let weights = options.score_weights.clone().unwrap_or_default();
+    let buffer = options.buffer;
+
+    let mut batch = VecDeque::new();
+    Box::new(std::iter::from_fn(move || {
+        if batch.is_empty() {
+            let proxies = match buffer {
+                0 => source.by_ref().collect::<Vec<_>>(),
+                _ => source.by_ref().take(buffer).collect(),
+            };
+            let (failed, mut working): (Vec<_>, Vec<_>) =
+                proxies.into_iter().partition(Proxy::is_failed);
+            score::rank(&mut working, &weights);
+            score::sort(&mut working, key);
+            batch.extend(working);
+            batch.extend(failed);
+        }
+        batch.pop_front()
+    }))
+}
+
 fn process_result<I>(source: I, options: Cli) -> anyhow::Result<()>
 where
-    I: Iterator<Item = Proxy>,
+    I: Iterator<Item = Proxy> + 'static,
 {
+    let source = sorted(source, &options);
     let mut output_file = options.output_file.map(|file_path| {
         File::options()
             .write(true)
@@ -84,17 +132,23 @@ where
             .unwrap()
     });
 
-    let mut found_proxy = false;
-    for (index, proxy) in source.enumerate() {
-        if !found_proxy {
-            found_proxy = true;
+    let mut written = 0;
+    let mut working = 0;
+    for proxy in source {
+        // Plain lists only hold working proxies, and only those count toward the limit
+        let is_failed = proxy.is_failed();
+        if is_failed && options.format == "text" {
+            continue;
+        }
+        if !is_failed {
+            working += 1;
         }
-        let should_end = options.limit > 0 && index + 1 >= options.limit;
+        let should_end = options.limit > 0 && working >= options.limit;
         let output = match options.format.as_str() {
             "text" => proxy.as_text().into_owned(),
             "json" => {
                 let mut json_output = String::new();
-                if index == 0 {
+                if written == 0 {
                     json_output.push_str("[\n");
                 }
                 json_output.push_str("  ");
@@ -113,13 +167,14 @@ where
         } else {
             println!("{}", output);
         }
+        written += 1;
 
         if should_end {
             break;
         }
     }
 
-    if found_proxy && options.format == "json" {
+    if written > 0 && options.format == "json" {
         if let Some(ref mut file) = output_file {
             file.write_all(b"]")?;
         } else {
@@ -129,6 +184,261 @@ where
     Ok(())
 }
 
+fn geo_config(options: &Cli) -> geolookup::Config {
+    let mut config = geolookup::Config {
+        max_age: (options.geo_max_age > 0)
+            .then(|| Duration::from_secs(options.geo_max_age * 24 * 60 * 60)),
+        ..Default::default()
+    };
+    // Each database may be given either as a local file or as the URL to download it from
+    let locations = [
+        (&options.geo_db, &mut config.city_path, &mut config.city_url),
+        (
+            &options.geo_asn_db,
+            &mut config.asn_path,
+            &mut config.asn_url,
+        ),
+    ];
+    for (location, path, url) in locations {
+        match location {
+            Some(location)
+                if location.starts_with("http://") || location.starts_with("https://") =>
+            {
+                *url = Some(location.clone())
+            }
+            Some(location) => *path = Some(PathBuf::from(location)),
+            None => {}
+        }
+    }
+    config
+}
+
+async fn update_geo(asn: bool, options: &Cli) -> anyhow::Result<()> {
+    let config = geo_config(options);
+    let mut databases = vec![Database::City];
+    if asn {
+        databases.push(Database::Asn);
+    }
+    for database in databases {
+        let path = geolookup::update(database, &config).await?;
+        println!("Updated {} at {}", database.name(), path.display());
+    }
+    Ok(())
+}
+
+fn geo_filter(options: &Cli) -> GeoFilter {
+    GeoFilter {
+        continents: options.continents.clone(),
+        exclude_continents: options.exclude_continents.clone(),
+        countries: options.countries.clone(),
+        exclude_countries: options.exclude_countries.clone(),
+        regions: options.regions.clone(),
+        exclude_regions: options.exclude_regions.clone(),
+        cities: options.cities.clone(),
+        exclude_cities: options.exclude_cities.clone(),
+        asns: options.asns.clone(),
+        exclude_asns: options.exclude_asns.clone(),
+        within: options.within,
+        eu_only: options.eu_only,
+    }
+}
+
+fn fetcher_config(options: &Cli) -> fetcher::Config {
+    fetcher::Config {
+        request_timeout: options.timeout * 1000,
+        concurrency_limit: 10,
+        // Location filters target either the listening address or the exit address
+        geo_filter: if options.geo_exit {
+            GeoFilter::default()
+        } else {
+            geo_filter(options)
+        },
+        enable_asn_lookup: options.asn,
+        geolookup: geo_config(options),
+        use_cache: !options.no_cache,
+        offline: options.offline,
+        files: options.files.clone(),
+        use_providers: options.files.is_empty() || options.providers,
+        queue_size: options.queue_size as usize,
+        dedup: match options.dedup.as_str() {
+            "none" => DedupMode::None,
+            "ip" => DedupMode::Ip,
+            _ => DedupMode::IpPort,
+        },
+        merge_duplicates: options.merge_duplicates,
+        seed: options.skip_seen.clone(),
+        ..Default::default()
+    }
+}
+
+fn validator_config(options: &Cli, types: Vec<Protocol>) -> fluxy::validator::Config {
+    fluxy::validator::Config {
+        types,
+        concurrency_limit: options.max_connections as usize,
+        max_attempts: options.max_attempts,
+        request_timeout: options.timeout,
+        queue_size: options.queue_size as usize,
+        enable_geo_lookup: options.geo_exit,
+        enable_asn_lookup: options.asn,
+        geolookup: geo_config(options),
+        exit_geo_filter: if options.geo_exit {
+            geo_filter(options)
+        } else {
+            GeoFilter::default()
+        },
+        ip_detector: if options.public_ip.is_empty() {
+            IpDetector::default()
+        } else {
+            IpDetector::fixed(&options.public_ip)
+        },
+        reliability_checks: options.checks as usize,
+        reliability_interval: options.check_interval,
+        min_success_rate: options.min_success_rate,
+        https_judge: options.https_judge.clone(),
+        https_judge_certificate: options.https_judge_cert.clone(),
+        drop_intercepted: options.drop_intercepted,
+        enable_bandwidth_test: options.bandwidth,
+        bandwidth_url: options.bandwidth_url.clone(),
+        bandwidth_bytes: options.bandwidth_bytes,
+        min_bandwidth: options.min_bandwidth.map(|kbps| kbps * 1000.0),
+        enable_tamper_check: options.tamper_check,
+        tamper_url: options.tamper_url.clone(),
+        tamper_sha256: options.tamper_sha256.clone(),
+        drop_tampered: options.drop_tampered,
+        report_failures: options.report_failures,
+    }
+}
+
+/// Fetch statistics of a source, along with the validation results of its proxies.
+#[derive(Serialize)]
+struct SourceReport {
+    #[serde(flatten)]
+    stats: SourceStats,
+    checked: Option<usize>, // Number of proxies from the source that were validated.
+    working: Option<usize>, // Number of those proxies that passed validation.
+}
+
+async fn report_sources(report: bool, validate: bool, options: &Cli) -> anyhow::Result<()> {
+    if !report {
+        for provider in fetcher::providers(&fetcher_config(options)) {
+            for source in provider.sources()? {
+                let types = source
+                    .default_types
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                println!("{} [{}]", source.url, types.join(", "));
+            }
+        }
+        return Ok(());
+    }
+
+    let mut config = fetcher_config(options);
+    // Locations are not reported, only looked up for the filters
+    config.enable_geo_lookup = false;
+    let mut fetcher = ProxyFetcher::gather(config).await?;
+    let proxies = fetcher.by_ref().collect::<Vec<_>>();
+
+    // Tally proxies per source, by address since each checked protocol yields a result
+    let mut checked: HashMap<String, usize> = HashMap::new();
+    let mut working: HashMap<String, usize> = HashMap::new();
+    if validate {
+        let types = if options.types.is_empty() {
+            vec![Protocol::Http(Anonymity::Unknown)]
+        } else {
+            convert_protocols(&options.types)
+        };
+        // Only proxies expected to support one of the types are sent to the checks
+        let is_checked = |proxy: &Proxy| {
+            proxy
+                .expected_types
+                .iter()
+                .any(|expected| types.iter().any(|protocol| expected.is_same_kind(protocol)))
+        };
+        for proxy in proxies.iter().filter(|proxy| is_checked(proxy)) {
+            for origin in &proxy.sources {
+                *checked.entry(origin.url.clone()).or_default() += 1;
+            }
+        }
+
+        let config = fluxy::validator::Config {
+            enable_geo_lookup: options.geo_exit,
+            report_failures: false,
+            ..validator_config(options, types)
+        };
+        let validator = ProxyValidator::validate(proxies.into_iter(), config).await?;
+
+        let mut counted = std::collections::HashSet::new();
+        for proxy in validator {
+            if counted.insert(proxy.as_text()) {
+                for origin in &proxy.sources {
+                    *working.entry(origin.url.clone()).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let reports = fetcher
+        .stats()
+        .into_iter()
+        .map(|stats| SourceReport {
+            checked: validate.then(|| checked.get(&stats.url).copied().unwrap_or(0)),
+            working: validate.then(|| working.get(&stats.url).copied().unwrap_or(0)),
+            stats,
+        })
+        .collect::<Vec<_>>();
+    if options.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+
+    print!(
+        "{:<6} {:>10} {:>8} {:>8} {:>8}",
+        "STATUS", "BYTES", "TIME", "PARSED", "DUPS"
+    );
+    if validate {
+        print!(" {:>8} {:>8} {:>6}", "CHECKED", "WORKING", "RATE");
+    }
+    println!("  URL");
+    for SourceReport {
+        stats: source,
+        checked,
+        working,
+    } in reports
+    {
+        let status = match (source.status, source.from_cache) {
+            (Some(status), true) => format!("{}*", status),
+            (Some(status), false) => status.to_string(),
+            (None, true) => "cache".to_string(),
+            (None, false) => "--".to_string(),
+        };
+        print!(
+            "{:<6} {:>10} {:>7.2}s {:>8} {:>8}",
+            status, source.bytes, source.duration, source.parsed, source.duplicates
+        );
+        let mut indent = 44;
+        if let (Some(checked), Some(working)) = (checked, working) {
+            let rate = match checked {
+                0 => "--".to_string(),
+                _ => format!("{:.0}%", working as f64 * 100.0 / checked as f64),
+            };
+            print!(" {:>8} {:>8} {:>6}", checked, working, rate);
+            indent += 25;
+        }
+        println!("  {}", source.url);
+        if let Some(final_url) = &source.final_url {
+            println!("{:>indent$}  -> {}", "", final_url);
+        }
+        if let Some(error) = &source.error {
+            println!("{:>indent$}  !! {}", "", error);
+        }
+        if let Some(page_error) = &source.page_error {
+            println!("{:>indent$}  .. {}", "", page_error);
+        }
+    }
+    Ok(())
+}
+
 fn run_application() -> anyhow::Result<()> {
     let options = Cli::parse();
 
@@ -147,36 +457,26 @@ fn run_application() -> anyhow::Result<()> {
 
     let runtime = runtime::Builder::new_multi_thread().enable_all().build()?;
     runtime.block_on(async {
-        let proxy_source: Box<dyn Iterator<Item = Proxy> + Send + 'static> =
-            if let Some(file) = &options.file {
-                let source = ProxySource::from_file(file.clone())?;
-                Box::new(source)
-            } else {
-                let source = ProxySource::from_fetcher(fluxy::fetcher::Config {
-                    request_timeout: options.timeout,
-                    concurrency_limit: 10,
-                    countries: options.countries.clone(),
-                    ..Default::default()
-                })
-                .await?;
-                Box::new(source)
-            };
+        match &options.command {
+            Some(Command::Sources { report, validate }) => {
+                return report_sources(*report, *validate, &options).await
+            }
+            Some(Command::Geo {
+                command: GeoCommand::Update { asn },
+            }) => return update_geo(*asn, &options).await,
+            None => {}
+        }
+
+        let proxy_source = ProxySource::from_fetcher(fetcher_config(&options)).await?;
 
         if !options.types.is_empty() {
             let protocols = convert_protocols(&options.types);
             if protocols.is_empty() {
                 std::process::exit(-1)
             }
-            let validated_proxies = ProxyValidator::validate(
-                proxy_source,
-                fluxy::validator::Config {
-                    types: protocols,
-                    concurrency_limit: options.max_connections as usize,
-                    max_attempts: options.max_attempts,
-                    request_timeout: options.timeout,
-                },
-            )
-            .await?;
+            let validated_proxies =
+                ProxyValidator::validate(proxy_source, validator_config(&options, protocols))
+                    .await?;
             process_result(validated_proxies, options)?;
         } else {
             process_result(proxy_source, options)?;
@@ -0,0 +1,172 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{proxy::models::Protocol, resolver::DetectError};
+
+/// Errors returned by the library.
+///
+/// Proxy connections, negotiations and checks report why they failed through the dedicated
+/// variants; errors from providers, caches and the GeoIP databases are wrapped in `Other`.
+#[derive(Debug)]
+pub enum Error {
+    /// The proxy did not accept the TCP connection in time.
+    ConnectTimeout,
+    /// The proxy refused the TCP connection.
+    ConnectionRefused,
+    /// The request through the proxy did not complete in time.
+    Timeout,
+    /// The proxy rejected the handshake or answered it with invalid data.
+    Negotiation {
+        protocol: Protocol, // Protocol being negotiated.
+        code: Option<u16>,  // Reply code sent by the proxy, if any.
+        reason: String,     // Description of the failure.
+    },
+    /// The proxy requires authentication.
+    AuthRequired {
+        protocol: Protocol, // Protocol being negotiated.
+    },
+    /// The TLS handshake through the proxy failed.
+    Tls(native_tls::Error),
+    /// The HTTP exchange through the proxy failed.
+    Http(hyper::Error),
+    /// The judge answered with an error or a response that could not be read.
+    Judge {
+        url: String,         // URL of the judge.
+        status: Option<u16>, // HTTP status of the response, if one was received.
+        reason: String,      // Description of the failure.
+    },
+    /// The public address of this machine could not be detected.
+    PublicIp(DetectError),
+    /// The configuration is invalid.
+    Config(String),
+    /// An I/O error occurred on the connection.
+    Io(std::io::Error),
+    /// Any other error.
+    Other(anyhow::Error),
+}
+
+/// A result with the library's `Error`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Creates a `Negotiation` error.
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol`: The protocol being negotiated.
+    /// * `code`: The reply code sent by the proxy, if any.
+    /// * `reason`: The description of the failure.
+    ///
+    /// # Returns
+    ///
+    /// A new `Error::Negotiation`.
+    pub fn negotiation(protocol: Protocol, code: Option<u16>, reason: impl Into<String>) -> Self {
+        Self::Negotiation {
+            protocol,
+            code,
+            reason: reason.into(),
+        }
+    }
+
+    /// Returns a short, stable identifier of the kind of error, suited to reports and metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ConnectTimeout => "connect_timeout",
+            Self::ConnectionRefused => "connection_refused",
+            Self::Timeout => "timeout",
+            Self::Negotiation { .. } => "negotiation",
+            Self::AuthRequired { .. } => "auth_required",
+            Self::Tls(_) => "tls",
+            Self::Http(_) => "http",
+            Self::Judge { .. } => "judge",
+            Self::PublicIp(_) => "public_ip",
+            Self::Config(_) => "config",
+            Self::Io(_) => "io",
+            Self::Other(_) => "other",
+        }
+    }
+
+    /// Indicates if the error is a connect or request timeout.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::ConnectTimeout | Self::Timeout)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ConnectTimeout => write!(f, "Connection timed out"),
+            Self::ConnectionRefused => write!(f, "Connection refused"),
+            Self::Timeout => write!(f, "Request timed out"),
+            Self::Negotiation {
+                protocol,
+                code: Some(code),
+                reason,
+            } => write!(f, "{} negotiation failed ({}): {}", protocol, code, reason),
+            Self::Negotiation {
+                protocol,
+                code: None,
+                reason,
+            } => write!(f, "{} negotiation failed: {}", protocol, reason),
+            Self::AuthRequired { protocol } => write!(f, "{} authentication is required", protocol),
+            Self::Tls(e) => write!(f, "TLS handshake failed: {}", e),
+            Self::Http(e) => write!(f, "HTTP request failed: {}", e),
+            Self::Judge {
+                url,
+                status: Some(status),
+                reason,
+            } => write!(f, "{}: Judge answered {}: {}", url, status, reason),
+            Self::Judge {
+                url,
+                status: None,
+                reason,
+            } => write!(f, "{}: Judge failed: {}", url, reason),
+            Self::PublicIp(e) => write!(f, "{}", e),
+            Self::Config(reason) => write!(f, "Invalid configuration: {}", reason),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Tls(e) => Some(e),
+            Self::Http(e) => Some(e),
+            Self::PublicIp(e) => Some(e),
+            Self::Io(e) => Some(e),
+            Self::Other(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<hyper::Error> for Error {
+    fn from(e: hyper::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+impl From<native_tls::Error> for Error {
+    fn from(e: native_tls::Error) -> Self {
+        Self::Tls(e)
+    }
+}
+
+impl From<DetectError> for Error {
+    fn from(e: DetectError) -> Self {
+        Self::PublicIp(e)
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Other(e)
+    }
+}
@@ -1,25 +1,68 @@
+use std::{path::PathBuf, sync::Arc};
+
+use super::DedupMode;
+use crate::{
+    geolookup::{self, GeoFilter},
+    providers::IProxyTrait,
+};
+
 /// Options for configuring the proxy fetching process.
 pub struct Config {
-    /// Ensure each proxy has a unique IP; affects performance.
-    pub enforce_unique_ip: bool,
+    /// Which proxies are considered the same; only the first sighting is kept, unless
+    /// `merge_duplicates` is set. Affects performance.
+    pub dedup: DedupMode,
+    /// Merge the protocols of every sighting of a proxy; waits for gathering to complete.
+    pub merge_duplicates: bool,
+    /// Output of a previous run; proxies listed in it are skipped (optional).
+    pub seed: Option<PathBuf>,
     /// Maximum number of concurrent requests to process source URLs.
     pub concurrency_limit: usize,
-    /// Timeout for requests in milliseconds.
+    /// Timeout for each source request in milliseconds, unless the source sets its own.
     pub request_timeout: u64,
     /// Perform geo lookup for each proxy; affects performance.
     pub enable_geo_lookup: bool,
-    /// Filter proxies by ISO country code; if empty, skip filtering (optional).
-    pub countries: Vec<String>,
+    /// Location, download URL and refresh age of the GeoIP databases.
+    pub geolookup: geolookup::Config,
+    /// Also look up the autonomous system of each proxy; requires `enable_geo_lookup`.
+    pub enable_asn_lookup: bool,
+    /// Filter proxies by continent, country, region and city; a non-empty filter enables the
+    /// geo lookup.
+    pub geo_filter: GeoFilter,
+    /// Cache source bodies in the data directory and revalidate them on later runs.
+    pub use_cache: bool,
+    /// Only read sources from the local cache; never touch the network.
+    pub offline: bool,
+    /// Maximum number of redirects to follow for each source.
+    pub max_redirects: usize,
+    /// Local files, directories, glob patterns or `-` (stdin) to read proxies from.
+    pub files: Vec<PathBuf>,
+    /// Fetch proxies from the built-in providers in addition to `files`.
+    pub use_providers: bool,
+    /// Additional providers to fetch proxies from, e.g. for sites not built in.
+    pub providers: Vec<Arc<dyn IProxyTrait + Send + Sync>>,
+    /// Maximum number of fetched proxies waiting to be retrieved.
+    pub queue_size: usize,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            enforce_unique_ip: true,
+            dedup: DedupMode::IpPort,
+            merge_duplicates: false,
+            seed: None,
             concurrency_limit: 10,
             request_timeout: 3000,
             enable_geo_lookup: true,
-            countries: Vec::new(),
+            geolookup: geolookup::Config::default(),
+            enable_asn_lookup: false,
+            geo_filter: GeoFilter::default(),
+            use_cache: true,
+            offline: false,
+            max_redirects: 10,
+            files: Vec::new(),
+            use_providers: true,
+            providers: Vec::new(),
+            queue_size: 1000,
         }
     }
 }
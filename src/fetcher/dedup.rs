@@ -0,0 +1,92 @@
+use std::{fs, net::Ipv4Addr, path::Path};
+
+use hashbrown::HashSet;
+
+use crate::{providers::decoders::extract_addresses, proxy::models::Proxy};
+
+/// Determines which proxies are considered the same when deduplicating.
+///
+/// Only the first sighting of a proxy is kept, with its own protocols and source; later
+/// sightings add theirs only with `fetcher::Config::merge_duplicates`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupMode {
+    /// Keep every sighting.
+    None,
+    /// Keep one proxy per IP address, whatever its port.
+    Ip,
+    /// Keep one proxy per `<ip>:<port>` pair.
+    IpPort,
+}
+
+impl DedupMode {
+    /// Returns the key identifying the given address, or `None` if nothing is deduplicated.
+    pub(crate) fn key(&self, ip: &Ipv4Addr, port: u16) -> Option<String> {
+        match self {
+            Self::None => None,
+            Self::Ip => Some(ip.to_string()),
+            Self::IpPort => Some(format!("{}:{}", ip, port)),
+        }
+    }
+
+    /// Returns the key identifying the given proxy, or `None` if nothing is deduplicated.
+    pub(crate) fn proxy_key(&self, proxy: &Proxy) -> Option<String> {
+        self.key(&proxy.ip, proxy.port)
+    }
+}
+
+/// Adds the protocols and sources of a duplicate sighting to the proxy kept for it.
+///
+/// Sightings on another port, under `DedupMode::Ip`, are dropped: their protocols were only
+/// seen on that port.
+pub(crate) fn merge(kept: &mut Proxy, duplicate: Proxy) {
+    if duplicate.port != kept.port {
+        return;
+    }
+    for protocol in duplicate.expected_types {
+        if !kept.expected_types.contains(&protocol) {
+            kept.expected_types.push(protocol);
+        }
+    }
+    for origin in duplicate.sources {
+        if !kept.seen_in(&origin.url) {
+            kept.sources.push(origin);
+        }
+    }
+}
+
+/// Reads the keys of the proxies listed in a previous run's output.
+///
+/// Every output format is understood: JSON lines are parsed for their `ip` and `port`
+/// fields, other lines are scanned for `<ip>:<port>` addresses.
+///
+/// # Arguments
+///
+/// * `path`: The path of the previous output.
+/// * `mode`: The deduplication mode the keys are built for.
+///
+/// # Returns
+///
+/// A result containing the set of keys, or an error if the file cannot be read.
+pub(crate) fn read_seed(path: &Path, mode: DedupMode) -> anyhow::Result<HashSet<String>> {
+    let content = fs::read_to_string(path)?;
+    let mut keys = HashSet::new();
+
+    for line in content.lines() {
+        let line = line.trim().trim_end_matches(',');
+        if line.starts_with('{') {
+            let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+                continue;
+            };
+            let ip = value["ip"].as_str().and_then(|ip| ip.parse().ok());
+            let port = value["port"].as_u64().and_then(|port| port.try_into().ok());
+            if let (Some(ip), Some(port)) = (ip, port) {
+                keys.extend(mode.key(&ip, port));
+            }
+        } else {
+            for address in extract_addresses(line) {
+                keys.extend(mode.key(address.ip(), address.port()));
+            }
+        }
+    }
+    Ok(keys)
+}
@@ -1,9 +1,16 @@
 mod config;
+mod dedup;
+mod stats;
 
-use std::{borrow::Cow, sync::Arc, time::Duration};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 pub use config::Config;
-use hashbrown::HashSet;
+pub use dedup::DedupMode;
+use hashbrown::{HashMap, HashSet};
 use http_body_util::Empty;
 use hyper::body::Bytes;
 use hyper_tls::HttpsConnector;
@@ -11,26 +18,57 @@ use hyper_util::{
     client::legacy::{connect::HttpConnector, Client},
     rt::TokioExecutor,
 };
+pub use stats::SourceStats;
 use tokio::{sync::Semaphore, task::JoinHandle, time};
 
 use crate::{
     geolookup::GeoLookup,
     providers::{
-        models::Source, FreeProxyListProvider, GithubRepoProvider, IProxyTrait, ProxyscrapeProvider,
+        cache::SourceCache,
+        models::{FetchOptions, Source},
+        FreeProxyListProvider, GithubRepoProvider, IProxyTrait, LocalProvider, ProxyscrapeProvider,
     },
-    proxy::models::Proxy,
+    proxy::models::{Proxy, ProxyOrigin},
+    Error,
 };
 
+/// Returns the proxy providers enabled by the given configuration.
+///
+/// # Arguments
+///
+/// * `config`: The configuration selecting built-in providers and local files.
+///
+/// # Returns
+///
+/// A vector of providers whose sources are fetched by `ProxyFetcher`, ending with
+/// `Config::providers`.
+pub fn providers(config: &Config) -> Vec<Arc<dyn IProxyTrait + Send + Sync>> {
+    let mut providers: Vec<Arc<dyn IProxyTrait + Send + Sync>> = vec![];
+    if config.use_providers {
+        providers.push(Arc::new(GithubRepoProvider));
+        providers.push(Arc::new(ProxyscrapeProvider));
+        providers.push(Arc::new(FreeProxyListProvider));
+    }
+    if !config.files.is_empty() {
+        providers.push(Arc::new(LocalProvider::new(config.files.clone())));
+    }
+    providers.extend(config.providers.iter().cloned());
+    providers
+}
+
 /// Responsible for fetching proxies from various sources.
 pub struct ProxyFetcher {
-    receiver: kanal::Receiver<Proxy>, // Channel receiver for receiving proxies.
-    counter: usize,                   // Counter for tracking the number of fetched proxies.
-    timer: time::Instant,             // Timer for measuring elapsed time.
-    elapsed: Option<Duration>,        // Duration of the fetcher operation.
-    geolookup: Option<GeoLookup>,     // Optional GeoIP instance for location lookups.
-    unique_ips: HashSet<Cow<'static, str>>, // Set to track unique IPs.
-    handlers: Vec<JoinHandle<()>>,    // Handle for the fetching task.
-    config: Config,                   // Configuration for the proxy fetcher.
+    receiver: kanal::Receiver<(usize, Proxy)>, // Channel receiver for proxies and their source index.
+    counter: usize,            // Counter for tracking the number of fetched proxies.
+    timer: time::Instant,      // Timer for measuring elapsed time.
+    elapsed: Option<Duration>, // Duration of the fetcher operation.
+    geolookup: Option<GeoLookup>, // Optional GeoIP instance for location lookups.
+    seeded: HashSet<String>,   // Keys of the proxies listed in the seed, which are skipped.
+    returned: HashSet<String>, // Keys of the proxies already returned, while streaming.
+    merged: Option<VecDeque<Proxy>>, // Merged proxies, once gathering completed (merge mode only).
+    stats: Arc<Mutex<Vec<SourceStats>>>, // Statistics for each source.
+    handlers: Vec<JoinHandle<()>>, // Handle for the fetching task.
+    config: Config,            // Configuration for the proxy fetcher.
 }
 
 impl ProxyFetcher {
@@ -43,19 +81,23 @@ impl ProxyFetcher {
     /// # Returns
     ///
     /// A result containing the initialized `ProxyFetcher`.
-    pub async fn gather(config: Config) -> anyhow::Result<Self> {
-        let (sender, receiver) = kanal::unbounded_async();
-        let geolookup = if config.enable_geo_lookup {
-            Some(GeoLookup::new().await?)
+    pub async fn gather(config: Config) -> crate::Result<Self> {
+        let (sender, receiver) = kanal::bounded_async(config.queue_size);
+        let geolookup = if config.enable_geo_lookup || !config.geo_filter.is_empty() {
+            let mut geolookup = GeoLookup::with_config(config.geolookup.clone()).await?;
+            if config.enable_asn_lookup || config.geo_filter.uses_asn() {
+                geolookup.enable_asn().await?;
+            }
+            Some(geolookup)
         } else {
             None
         };
 
-        let providers: Vec<Arc<dyn IProxyTrait + Send + Sync>> = vec![
-            Arc::new(GithubRepoProvider),
-            Arc::new(ProxyscrapeProvider),
-            Arc::new(FreeProxyListProvider),
-        ];
+        let providers = providers(&config);
+        let seeded = match &config.seed {
+            Some(path) => dedup::read_seed(path, config.dedup)?,
+            None => HashSet::new(),
+        };
 
         let mut fetcher = Self {
             receiver: receiver.to_sync(),
@@ -63,40 +105,87 @@ impl ProxyFetcher {
             timer: time::Instant::now(),
             elapsed: None,
             handlers: vec![],
-            unique_ips: HashSet::new(),
+            seeded,
+            returned: HashSet::new(),
+            merged: None,
+            stats: Arc::new(Mutex::new(vec![])),
             geolookup,
             config,
         };
 
         let mut tasks = vec![];
         for provider in providers.iter() {
-            for source in provider.sources() {
+            let sources = provider
+                .sources()
+                .map_err(|e| Error::Config(format!("{} provider: {}", provider.name(), e)))?;
+            for source in sources {
                 tasks.push((Arc::new(source), Arc::clone(provider)));
             }
         }
 
+        if let Ok(mut stats) = fetcher.stats.lock() {
+            stats.extend(
+                tasks
+                    .iter()
+                    .map(|(source, _)| SourceStats::new(source.url.to_string())),
+            );
+        }
+
         #[cfg(feature = "log")]
         log::debug!("Proxy gathering started ({} sources)", tasks.len(),);
 
-        let client = Arc::new(
-            Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpsConnector::new()),
-        );
-        let concurrency_limit = fetcher.config.concurrency_limit;
-        let sem = Arc::new(Semaphore::new(concurrency_limit));
+        let cache = if fetcher.config.use_cache || fetcher.config.offline {
+            match SourceCache::open() {
+                Ok(cache) => Some(Arc::new(cache)),
+                Err(e) => {
+                    if fetcher.config.offline {
+                        return Err(e.into());
+                    }
+                    #[cfg(feature = "log")]
+                    log::warn!("Failed to open source cache: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let options = Arc::new(FetchOptions {
+            cache,
+            offline: fetcher.config.offline,
+            max_redirects: fetcher.config.max_redirects,
+        });
+
+        let context = FetchContext {
+            client: Arc::new(
+                Client::builder(TokioExecutor::new())
+                    .build::<_, Empty<Bytes>>(HttpsConnector::new()),
+            ),
+            tx: sender,
+            options,
+            timeout: Duration::from_millis(fetcher.config.request_timeout),
+            sem: Arc::new(Semaphore::new(fetcher.config.concurrency_limit)),
+        };
 
-        for (source, provider) in tasks {
-            let permit = Arc::clone(&sem);
-            let client = Arc::clone(&client);
-            let tx = sender.clone();
+        for (index, (source, provider)) in tasks.into_iter().enumerate() {
+            let context = context.clone();
+            let stats = Arc::clone(&fetcher.stats);
 
             fetcher.handlers.push(tokio::spawn(async move {
-                if permit.acquire().await.is_ok() {
-                    let url = source.url.to_string();
-                    if let Err(e) = do_work(provider, client, source, tx).await {
-                        #[cfg(feature = "log")]
-                        log::error!("{}: {}", url, e);
-                    }
+                let url = source.url.to_string();
+                let mut source_stats = SourceStats::new(url.clone());
+                if let Err(e) = do_work(provider, source, index, &context, &mut source_stats).await
+                {
+                    #[cfg(feature = "log")]
+                    log::error!("{}: {}", url, e);
+                    source_stats.error = Some(e.to_string());
+                }
+                if let Ok(mut stats) = stats.lock() {
+                    // Duplicates are counted by the receiving side, keep them
+                    source_stats.duplicates = stats[index].duplicates;
+                    stats[index] = source_stats;
                 }
+                // Release the sender last, so the statistics are final once the channel closes
+                drop(context);
             }));
         }
 
@@ -104,63 +193,196 @@ impl ProxyFetcher {
     }
 }
 
+/// State shared by the tasks fetching the sources.
+#[derive(Clone)]
+struct FetchContext {
+    client: Arc<Client<HttpsConnector<HttpConnector>, Empty<Bytes>>>, // HTTP client for every source.
+    tx: kanal::AsyncSender<(usize, Proxy)>, // Channel sender for proxies and their source index.
+    options: Arc<FetchOptions>,             // Cache, offline and redirect settings.
+    timeout: Duration,                      // Request timeout of sources without their own.
+    sem: Arc<Semaphore>,                    // Limits the concurrent requests, pages included.
+}
+
 /// Executes the work of fetching proxies from a given provider.
+///
+/// Paginated sources are walked page by page, each fetch holding a permit of the shared
+/// semaphore, and the walk stops early when a page yields no new proxies or fails.
 async fn do_work(
     provider: Arc<dyn IProxyTrait + Send + Sync>,
-    client: Arc<Client<HttpsConnector<HttpConnector>, Empty<Bytes>>>,
     source: Arc<Source>,
-    tx: kanal::AsyncSender<Proxy>,
+    index: usize,
+    context: &FetchContext,
+    stats: &mut SourceStats,
 ) -> anyhow::Result<()> {
-    let html = provider
-        .fetch(client, &source.url.to_string(), source.timeout)
-        .await?;
-    let expected_types = source.default_types.clone();
-    provider.scrape(html, tx, expected_types).await
+    let mut seen = HashSet::new();
+
+    for (page, url) in source.urls().into_iter().enumerate() {
+        let start_time = time::Instant::now();
+        let content = {
+            let _permit = context.sem.acquire().await?;
+            provider
+                .fetch(
+                    Arc::clone(&context.client),
+                    &url,
+                    source.timeout.unwrap_or(context.timeout),
+                    &context.options,
+                )
+                .await
+        };
+        stats.duration += start_time.elapsed().as_secs_f64();
+
+        let content = match content {
+            Ok(content) => content,
+            Err(e) if page == 0 => return Err(e),
+            Err(e) => {
+                // Sites usually answer past the last page with an error
+                #[cfg(feature = "log")]
+                log::debug!("{}: Stopped at page {}: {}", source.url, page + 1, e);
+                stats.page_error = Some(format!("{}: {}", url, e));
+                break;
+            }
+        };
+        stats.pages += 1;
+
+        if content.url != url {
+            #[cfg(feature = "log")]
+            log::info!("{}: Source moved to {}", url, content.url);
+            stats.final_url = Some(content.url.clone());
+        }
+        stats.status = content.status;
+        stats.bytes += content.bytes;
+        stats.from_cache = content.from_cache;
+
+        let expected_types = source.default_types.clone();
+        let proxies = provider.scrape(content.body, expected_types).await?;
+
+        // Repeats within the source are dropped here, those across sources by the receiver
+        let mut found_new = false;
+        for mut proxy in proxies {
+            if !seen.insert(proxy.as_text()) {
+                continue;
+            }
+            found_new = true;
+            stats.parsed += 1;
+            proxy
+                .sources
+                .push(ProxyOrigin::new(provider.name(), source.url.as_str()));
+            if context.tx.send((index, proxy)).await.is_err() {
+                return Ok(());
+            }
+        }
+
+        if !found_new {
+            break;
+        }
+    }
+    Ok(())
 }
 
 impl ProxyFetcher {
-    /// Retrieves one proxy from the receiver.
-    ///
-    /// If geo lookup is enabled, it will apply geographic filtering.
-    ///
-    /// # Returns
-    ///
-    /// An optional `Proxy` if one is available, otherwise `None`.
-    pub fn get_one(&mut self) -> Option<Proxy> {
+    /// Receives the next proxy passing the geographic filters, along with its source index.
+    fn receive(&mut self) -> Option<(usize, Proxy)> {
         while !self.receiver.is_empty() || self.receiver.sender_count() != 0 {
-            if let Ok(mut proxy) = self.receiver.recv_timeout(Duration::from_millis(100)) {
+            if let Ok((index, mut proxy)) = self.receiver.recv_timeout(Duration::from_millis(100)) {
                 if let Some(geolookup) = &self.geolookup {
                     proxy.geo = geolookup.lookup(&proxy.ip);
 
-                    if !self.config.countries.is_empty()
-                        && !proxy
-                            .geo
-                            .iso_code
-                            .clone()
-                            .map(|code| self.config.countries.contains(&code))
-                            .unwrap_or(false)
-                    {
+                    if !self.config.geo_filter.matches(&proxy.geo) {
                         continue;
                     }
                 }
+                return Some((index, proxy));
+            }
+        }
+        None
+    }
 
-                if self.config.enforce_unique_ip {
-                    if self.unique_ips.insert(proxy.as_text()) {
-                        self.counter += 1;
-                        return Some(proxy);
-                    } else {
-                        continue;
-                    }
-                } else {
-                    self.counter += 1;
-                    return Some(proxy);
+    /// Records a duplicate sighting for the source at the given index.
+    fn count_duplicate(&self, index: usize) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats[index].duplicates += 1;
+        }
+    }
+
+    /// Waits for gathering to complete, merging every sighting of a proxy into the first one.
+    fn collect_merged(&mut self) -> VecDeque<Proxy> {
+        let mut proxies: Vec<Proxy> = vec![];
+        let mut positions = HashMap::new();
+
+        while let Some((index, proxy)) = self.receive() {
+            let Some(key) = self.config.dedup.proxy_key(&proxy) else {
+                proxies.push(proxy);
+                continue;
+            };
+            if self.seeded.contains(&key) {
+                self.count_duplicate(index);
+                continue;
+            }
+            match positions.get(&key) {
+                Some(&position) => {
+                    dedup::merge(&mut proxies[position], proxy);
+                    self.count_duplicate(index);
+                }
+                None => {
+                    positions.insert(key, proxies.len());
+                    proxies.push(proxy);
                 }
             }
         }
+        proxies.into()
+    }
+
+    /// Retrieves one proxy from the receiver.
+    ///
+    /// If geo lookup is enabled, it will apply geographic filtering. Duplicates are dropped
+    /// according to `Config::dedup`: only the first sighting of a proxy is returned, with its
+    /// own protocols and source. With `Config::merge_duplicates`, the first call waits for
+    /// gathering to complete so every sighting is merged into a single proxy.
+    ///
+    /// # Returns
+    ///
+    /// An optional `Proxy` if one is available, otherwise `None`.
+    pub fn get_one(&mut self) -> Option<Proxy> {
+        if self.config.merge_duplicates {
+            if self.merged.is_none() {
+                self.merged = Some(self.collect_merged());
+            }
+            let proxy = self.merged.as_mut()?.pop_front()?;
+            self.counter += 1;
+            return Some(proxy);
+        }
+
+        while let Some((index, proxy)) = self.receive() {
+            if let Some(key) = self.config.dedup.proxy_key(&proxy) {
+                if self.seeded.contains(&key) || !self.returned.insert(key) {
+                    self.count_duplicate(index);
+                    continue;
+                }
+            }
+            self.counter += 1;
+            return Some(proxy);
+        }
         None
     }
 }
 
+impl ProxyFetcher {
+    /// Returns a snapshot of the statistics collected for each source.
+    ///
+    /// Fetch results are recorded as sources complete, while duplicates are counted as
+    /// proxies are retrieved, so the numbers are final once the fetcher is exhausted.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `SourceStats`, one per source.
+    pub fn stats(&self) -> Vec<SourceStats> {
+        self.stats
+            .lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+}
+
 impl Iterator for ProxyFetcher {
     type Item = Proxy;
 
@@ -172,7 +394,7 @@ impl Iterator for ProxyFetcher {
 impl Drop for ProxyFetcher {
     /// Cleans up resources when `ProxyFetcher` is dropped.
     fn drop(&mut self) {
-        self.receiver.close();
+        let _ = self.receiver.close();
         while let Some(handler) = self.handlers.pop() {
             handler.abort();
         }
@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+/// Statistics collected while fetching and parsing a single source.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceStats {
+    /// URL of the source.
+    pub url: String,
+    /// URL the source was served from, if it was redirected.
+    pub final_url: Option<String>,
+    /// HTTP status of the final response; `None` if no request was made.
+    pub status: Option<u16>,
+    /// Indicates if the body was read from the local cache.
+    pub from_cache: bool,
+    /// Number of pages fetched.
+    pub pages: usize,
+    /// Number of body bytes received from the source; 0 if served from the cache.
+    pub bytes: usize,
+    /// Time spent fetching the source, in seconds.
+    pub duration: f64,
+    /// Number of distinct proxies parsed from the source, across all pages; a proxy listed
+    /// several times by the source is counted once.
+    pub parsed: usize,
+    /// Number of parsed proxies dropped as duplicates, because another source or the seed
+    /// already listed them; the others were kept unless filtered out by location.
+    pub duplicates: usize,
+    /// Error that stopped the source from being fetched or parsed.
+    pub error: Option<String>,
+    /// Error that ended a paginated source after its first page; sites usually answer past
+    /// their last page with one.
+    pub page_error: Option<String>,
+}
+
+impl SourceStats {
+    /// Creates empty statistics for the given source URL.
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            ..Default::default()
+        }
+    }
+
+    /// Indicates if the source was fetched and parsed without errors.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
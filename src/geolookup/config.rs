@@ -0,0 +1,38 @@
+use std::{path::PathBuf, time::Duration};
+
+/// Default endpoint serving the GeoLite2-City database.
+pub const GEOLITE_ENDPOINT_URL: &str =
+    "https://raw.githubusercontent.com/P3TERX/GeoLite.mmdb/download/GeoLite2-City.mmdb";
+/// Default endpoint serving the GeoLite2-ASN database.
+pub const GEOLITE_ASN_ENDPOINT_URL: &str =
+    "https://raw.githubusercontent.com/P3TERX/GeoLite.mmdb/download/GeoLite2-ASN.mmdb";
+
+/// Options for locating, downloading and refreshing the GeoIP databases.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Path of the GeoLite2-City database; defaults to a file in the data directory.
+    pub city_path: Option<PathBuf>,
+    /// URL the GeoLite2-City database is downloaded from; defaults to `GEOLITE_ENDPOINT_URL`.
+    /// A database at a custom path is only refreshed if its URL is set.
+    pub city_url: Option<String>,
+    /// Path of the GeoLite2-ASN database; defaults to a file in the data directory.
+    pub asn_path: Option<PathBuf>,
+    /// URL the GeoLite2-ASN database is downloaded from; defaults to
+    /// `GEOLITE_ASN_ENDPOINT_URL`. A database at a custom path is only refreshed if its URL
+    /// is set.
+    pub asn_url: Option<String>,
+    /// Age after which a database is downloaded again; if `None`, it is never refreshed.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            city_path: None,
+            city_url: None,
+            asn_path: None,
+            asn_url: None,
+            max_age: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+        }
+    }
+}
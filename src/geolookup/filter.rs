@@ -0,0 +1,120 @@
+use super::models::GeoData;
+
+/// A circular area on the Earth's surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoRadius {
+    /// Latitude of the center, in degrees.
+    pub latitude: f64,
+    /// Longitude of the center, in degrees.
+    pub longitude: f64,
+    /// Radius of the area, in kilometers.
+    pub km: f64,
+}
+
+/// Include and exclude lists applied to the geographic data of proxies.
+///
+/// Every list is optional: an empty include list accepts any value, an empty exclude list
+/// rejects none. Codes and names are compared case-insensitively, and a proxy whose data
+/// is unknown never matches an include list.
+#[derive(Debug, Clone, Default)]
+pub struct GeoFilter {
+    /// Continent codes to keep, e.g. `EU`.
+    pub continents: Vec<String>,
+    /// Continent codes to drop.
+    pub exclude_continents: Vec<String>,
+    /// ISO country codes to keep, e.g. `US`.
+    pub countries: Vec<String>,
+    /// ISO country codes to drop.
+    pub exclude_countries: Vec<String>,
+    /// Region ISO codes to keep, either bare (`CA`) or prefixed by the country (`US-CA`).
+    pub regions: Vec<String>,
+    /// Region ISO codes to drop, in the same forms as `regions`.
+    pub exclude_regions: Vec<String>,
+    /// City names to keep.
+    pub cities: Vec<String>,
+    /// City names to drop.
+    pub exclude_cities: Vec<String>,
+    /// Autonomous system numbers to keep; requires ASN lookups.
+    pub asns: Vec<u32>,
+    /// Autonomous system numbers to drop, e.g. hosting providers; requires ASN lookups.
+    pub exclude_asns: Vec<u32>,
+    /// Keep only proxies located within this area; proxies without coordinates are dropped.
+    pub within: Option<GeoRadius>,
+    /// Keep only proxies located in a member country of the European Union.
+    pub eu_only: bool,
+}
+
+impl GeoFilter {
+    /// Indicates if the filter accepts every proxy.
+    pub fn is_empty(&self) -> bool {
+        self.continents.is_empty()
+            && self.exclude_continents.is_empty()
+            && self.countries.is_empty()
+            && self.exclude_countries.is_empty()
+            && self.regions.is_empty()
+            && self.exclude_regions.is_empty()
+            && self.cities.is_empty()
+            && self.exclude_cities.is_empty()
+            && self.asns.is_empty()
+            && self.exclude_asns.is_empty()
+            && self.within.is_none()
+            && !self.eu_only
+    }
+
+    /// Indicates if the filter needs the ASN database.
+    pub fn uses_asn(&self) -> bool {
+        !self.asns.is_empty() || !self.exclude_asns.is_empty()
+    }
+
+    /// Checks the geographic data of a proxy against the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `geo`: The geographic data of the proxy.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the proxy passes every include and exclude list.
+    pub fn matches(&self, geo: &GeoData) -> bool {
+        let region = |filter: &str| match (&geo.region_iso_code, filter.split_once('-')) {
+            (Some(region), Some((country, code))) => {
+                region.eq_ignore_ascii_case(code)
+                    && geo
+                        .iso_code
+                        .as_ref()
+                        .is_some_and(|iso_code| iso_code.eq_ignore_ascii_case(country))
+            }
+            (Some(region), None) => region.eq_ignore_ascii_case(filter),
+            (None, _) => false,
+        };
+
+        check(&self.continents, &self.exclude_continents, |continent| {
+            is_same(&geo.continent_code, continent)
+        }) && check(&self.countries, &self.exclude_countries, |country| {
+            is_same(&geo.iso_code, country)
+        }) && check(&self.regions, &self.exclude_regions, region)
+            && check(&self.cities, &self.exclude_cities, |city| {
+                is_same(&geo.city_name, city)
+            })
+            && (self.asns.is_empty() || geo.asn.is_some_and(|asn| self.asns.contains(&asn)))
+            && !geo.asn.is_some_and(|asn| self.exclude_asns.contains(&asn))
+            && self.within.is_none_or(|area| {
+                geo.distance_km(area.latitude, area.longitude)
+                    .is_some_and(|distance| distance <= area.km)
+            })
+            && (!self.eu_only || geo.is_in_european_union)
+    }
+}
+
+/// Compares an optional value with a filter entry, ignoring case.
+fn is_same(value: &Option<String>, filter: &str) -> bool {
+    value
+        .as_ref()
+        .is_some_and(|value| value.eq_ignore_ascii_case(filter))
+}
+
+/// Applies an include and an exclude list with the given predicate.
+fn check(include: &[String], exclude: &[String], is_match: impl Fn(&str) -> bool) -> bool {
+    (include.is_empty() || include.iter().any(|entry| is_match(entry)))
+        && !exclude.iter().any(|entry| is_match(entry))
+}
@@ -1,13 +1,15 @@
+mod config;
+mod filter;
 pub mod models;
 
 use std::{
-    env::current_dir,
     fmt::{Display, Formatter},
     fs::{self, remove_file, OpenOptions},
     io::Write,
     net::Ipv4Addr,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
 };
 
 #[cfg(feature = "progress_bar")]
@@ -17,18 +19,23 @@ use http_body_util::{BodyExt, Empty};
 use hyper::{body::Bytes, Request};
 use hyper_tls::HttpsConnector;
 use hyper_util::{client::legacy::Client, rt::TokioExecutor};
-use maxminddb::{geoip2::City, Reader};
+use maxminddb::{
+    geoip2::{Asn, City},
+    Reader,
+};
 use models::GeoData;
 #[cfg(feature = "progress_bar")]
 use status_line::StatusLine;
 use tokio::time;
 
-const GEOLITE_ENDPOINT_URL: &str =
-    "https://raw.githubusercontent.com/P3TERX/GeoLite.mmdb/download/GeoLite2-City.mmdb";
+use crate::data_dir;
+pub use config::{Config, GEOLITE_ASN_ENDPOINT_URL, GEOLITE_ENDPOINT_URL};
+pub use filter::{GeoFilter, GeoRadius};
 
 #[cfg(feature = "progress_bar")]
 /// Struct to manage and display progress for downloading the GeoLite2 database.
 struct Progress {
+    name: &'static str,    // Name of the downloaded database.
     progress: AtomicUsize, // Tracks the current progress.
     max: f64,              // Maximum size of the download.
     timer: time::Instant,  // Timer to measure download duration.
@@ -39,9 +46,10 @@ impl Display for Progress {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{} {} Downloading GeoLite2-City.mmdb: {:.2}%",
+            "{} {} Downloading {}: {:.2}%",
             format!("{}:", module_path!()).bright_blue(),
             "INFO".bright_blue(),
+            self.name,
             (self.progress.load(Ordering::Relaxed) as f64 / self.max) * 100.0
         )
     }
@@ -51,52 +59,50 @@ impl Display for Progress {
 impl Drop for Progress {
     fn drop(&mut self) {
         log::debug!(
-            "Finished downloading GeoLite2-City.mmdb in {:?}",
+            "Finished downloading {} in {:?}",
+            self.name,
             self.timer.elapsed()
         );
     }
 }
 
-/// Retrieves the data directory path for the application.
+/// Downloads the GeoLite2-City database from the default endpoint.
+///
+/// # Arguments
+///
+/// * `mmdb_path`: The path where the database file will be saved.
 ///
 /// # Returns
 ///
-/// A `PathBuf` representing the path to the data directory.
-fn data_dir() -> anyhow::Result<PathBuf> {
-    if let Some(base_dirs) = directories::BaseDirs::new() {
-        let mut dir = base_dirs.data_dir().to_path_buf();
-        dir.push(env!("CARGO_PKG_NAME"));
-
-        if !dir.is_dir() {
-            fs::create_dir(&dir)?;
-        }
-        Ok(dir)
-    } else {
-        #[cfg(feature = "log")]
-        log::warn!("Failed to get local data directory, using current directory instead");
-        Ok(current_dir().unwrap_or_default())
-    }
+/// A result indicating success or failure.
+pub async fn download_database(mmdb_path: &PathBuf) -> anyhow::Result<()> {
+    download(GEOLITE_ENDPOINT_URL, "GeoLite2-City.mmdb", mmdb_path).await
 }
 
-/// Downloads the GeoLite2 database from the specified endpoint if it does not exist.
+/// Downloads a database from the given endpoint.
 ///
 /// # Arguments
 ///
+/// * `url`: The endpoint serving the database.
+/// * `_name`: The name of the database, shown while downloading.
 /// * `mmdb_path`: The path where the database file will be saved.
 ///
 /// # Returns
 ///
 /// A result indicating success or failure.
-pub async fn download_database(mmdb_path: &PathBuf) -> anyhow::Result<()> {
+async fn download(url: &str, _name: &'static str, mmdb_path: &PathBuf) -> anyhow::Result<()> {
     let https_connector = HttpsConnector::new();
     let client = Client::builder(TokioExecutor::new()).build(https_connector);
 
     let req = Request::builder()
-        .uri(GEOLITE_ENDPOINT_URL)
+        .uri(url)
         .header(hyper::header::USER_AGENT, UserAgent().fake::<&str>())
         .body(Empty::<Bytes>::new())?;
 
     let mut response = client.request(req).await?;
+    if !response.status().is_success() {
+        anyhow::bail!("{}: Unexpected status {}", url, response.status());
+    }
 
     #[cfg(feature = "progress_bar")]
     let max_size = if let Some(length) = response.headers().get(hyper::header::CONTENT_LENGTH) {
@@ -107,6 +113,7 @@ pub async fn download_database(mmdb_path: &PathBuf) -> anyhow::Result<()> {
 
     #[cfg(feature = "progress_bar")]
     let status = StatusLine::new(Progress {
+        name: _name,
         progress: AtomicUsize::new(0),
         timer: time::Instant::now(),
         max: max_size,
@@ -131,9 +138,228 @@ pub async fn download_database(mmdb_path: &PathBuf) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The GeoLite2 databases read by `GeoLookup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Database {
+    /// GeoLite2-City: continent, country, region and city data.
+    City,
+    /// GeoLite2-ASN: autonomous system number and organisation.
+    Asn,
+}
+
+impl Database {
+    /// Returns the name of the database, shown in messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::City => "GeoLite2-City.mmdb",
+            Self::Asn => "GeoLite2-ASN.mmdb",
+        }
+    }
+
+    /// Returns the URL the database is downloaded from.
+    fn url<'a>(&self, config: &'a Config) -> &'a str {
+        match self {
+            Self::City => config.city_url.as_deref().unwrap_or(GEOLITE_ENDPOINT_URL),
+            Self::Asn => config
+                .asn_url
+                .as_deref()
+                .unwrap_or(GEOLITE_ASN_ENDPOINT_URL),
+        }
+    }
+
+    /// Indicates if the database may be replaced by a download.
+    ///
+    /// A database at a path chosen by the user is only replaced if its URL was configured
+    /// too, so that a custom file is never overwritten with the default download.
+    fn is_updatable(&self, config: &Config) -> bool {
+        let has_url = match self {
+            Self::City => config.city_url.is_some(),
+            Self::Asn => config.asn_url.is_some(),
+        };
+        !self.is_custom(config) || has_url
+    }
+
+    /// Indicates if the database lives at a path chosen by the user.
+    fn is_custom(&self, config: &Config) -> bool {
+        match self {
+            Self::City => config.city_path.is_some(),
+            Self::Asn => config.asn_path.is_some(),
+        }
+    }
+
+    /// Returns the path of the database, inside the data directory unless configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The configuration which may override the path.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the path of the database file.
+    pub fn path(&self, config: &Config) -> anyhow::Result<PathBuf> {
+        let path = match self {
+            Self::City => config.city_path.clone(),
+            Self::Asn => config.asn_path.clone(),
+        };
+        match path {
+            Some(path) => Ok(path),
+            None => Ok(data_dir()?.join(self.name().to_lowercase())),
+        }
+    }
+
+    /// Checks the metadata of an opened database.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader`: The opened database.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating if the database is of the expected type and not empty.
+    fn validate(&self, reader: &Reader<Vec<u8>>) -> anyhow::Result<()> {
+        let expected = match self {
+            Self::City => "City",
+            Self::Asn => "ASN",
+        };
+        let metadata = &reader.metadata;
+        if !metadata.database_type.contains(expected) {
+            anyhow::bail!(
+                "Expected a {} database, got {}",
+                self.name(),
+                metadata.database_type
+            );
+        }
+        if metadata.node_count == 0 {
+            anyhow::bail!("{} is empty", self.name());
+        }
+        Ok(())
+    }
+}
+
+/// Indicates if a file was last modified longer ago than the given age.
+fn is_stale(path: &Path, max_age: Option<Duration>) -> bool {
+    let Some(max_age) = max_age else {
+        return false;
+    };
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > max_age)
+}
+
+/// Downloads a database and replaces the current file with it once validated.
+///
+/// The download goes to a temporary file next to the database, which is only renamed over
+/// it if it opens, has the expected type and is not older than the current copy. A failed
+/// update therefore leaves the current database untouched. An existing database at a custom
+/// path is only replaced if its URL is configured.
+///
+/// # Arguments
+///
+/// * `database`: The database to update.
+/// * `config`: The configuration locating the database and its endpoint.
+///
+/// # Returns
+///
+/// A result containing the path of the updated database.
+pub async fn update(database: Database, config: &Config) -> crate::Result<PathBuf> {
+    let path = database.path(config)?;
+    if path.exists() && !database.is_updatable(config) {
+        return Err(crate::Error::Config(format!(
+            "{} is a custom database without a download URL; not replacing it",
+            path.display()
+        )));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp_path = path.clone().into_os_string();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let downloaded = async {
+        download(database.url(config), database.name(), &tmp_path).await?;
+        let reader = Reader::open_readfile(&tmp_path)?;
+        database.validate(&reader)?;
+        if let Ok(current) = Reader::open_readfile(&path) {
+            if reader.metadata.build_epoch < current.metadata.build_epoch {
+                anyhow::bail!(
+                    "Downloaded {} is older than the current one",
+                    database.name()
+                );
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    match downloaded {
+        Ok(()) => {
+            fs::rename(&tmp_path, &path)?;
+            #[cfg(feature = "log")]
+            log::debug!("Updated {} at {}", database.name(), path.display());
+            Ok(path)
+        }
+        Err(e) => {
+            let _ = remove_file(&tmp_path);
+            Err(e.into())
+        }
+    }
+}
+
+/// Opens a database, downloading it first if it does not exist or refreshing it if stale.
+///
+/// Custom databases without a configured URL are never refreshed.
+///
+/// # Arguments
+///
+/// * `database`: The database to open.
+/// * `config`: The configuration locating the database and its endpoint.
+///
+/// # Returns
+///
+/// A result containing the database reader.
+async fn open_database(database: Database, config: &Config) -> anyhow::Result<Reader<Vec<u8>>> {
+    let mmdb_path = database.path(config)?;
+
+    if !mmdb_path.exists() {
+        #[cfg(feature = "log")]
+        log::debug!("{} does not exist, downloading", database.name());
+        update(database, config).await?;
+    } else if database.is_updatable(config) && is_stale(&mmdb_path, config.max_age) {
+        #[cfg(feature = "log")]
+        log::debug!("{} is outdated, updating", database.name());
+        if let Err(_e) = update(database, config).await {
+            #[cfg(feature = "log")]
+            log::warn!(
+                "Failed to update {}: {}, using current copy",
+                database.name(),
+                _e
+            );
+        }
+    }
+
+    match Reader::open_readfile(&mmdb_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|reader| database.validate(&reader).map(|_| reader))
+    {
+        Ok(reader) => Ok(reader),
+        Err(e) => {
+            // Never delete a database the user pointed us at
+            if !database.is_custom(config) {
+                remove_file(mmdb_path)?;
+            }
+            anyhow::bail!(e);
+        }
+    }
+}
+
 /// Manages the GeoIP database and provides lookup functionality.
 pub struct GeoLookup {
-    reader: Reader<Vec<u8>>, // Reader for the GeoLite2 database.
+    reader: Reader<Vec<u8>>,             // Reader for the GeoLite2 database.
+    asn_reader: Option<Reader<Vec<u8>>>, // Reader for the optional GeoLite2-ASN database.
+    config: Config,                      // Configuration locating the databases.
 }
 
 impl GeoLookup {
@@ -142,23 +368,42 @@ impl GeoLookup {
     /// # Returns
     ///
     /// A result containing the initialized `GeoLookup` instance.
-    pub async fn new() -> anyhow::Result<Self> {
-        let mut mmdb_path = data_dir()?;
-        mmdb_path.set_file_name("geolite2-city.mmdb");
+    pub async fn new() -> crate::Result<Self> {
+        Self::with_config(Config::default()).await
+    }
 
-        if !mmdb_path.exists() {
-            #[cfg(feature = "log")]
-            log::debug!("Geolite2-city.mmdb does not exist, downloading");
-            download_database(&mmdb_path).await?;
-        }
+    /// Creates a new instance of `GeoLookup` with the given configuration, downloading or
+    /// refreshing the GeoLite2 database if necessary.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The configuration locating the databases.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the initialized `GeoLookup` instance.
+    pub async fn with_config(config: Config) -> crate::Result<Self> {
+        let reader = open_database(Database::City, &config).await?;
+        Ok(Self {
+            reader,
+            asn_reader: None,
+            config,
+        })
+    }
 
-        match Reader::open_readfile(&mmdb_path) {
-            Ok(reader) => Ok(Self { reader }),
-            Err(e) => {
-                remove_file(mmdb_path)?;
-                anyhow::bail!(e);
-            }
+    /// Enables ASN lookups, downloading the GeoLite2-ASN database if necessary.
+    ///
+    /// Once enabled, `lookup` also fills the autonomous system number and organisation.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating success or failure.
+    pub async fn enable_asn(&mut self) -> crate::Result<()> {
+        if self.asn_reader.is_none() {
+            let reader = open_database(Database::Asn, &self.config).await?;
+            self.asn_reader = Some(reader);
         }
+        Ok(())
     }
 
     /// Looks up geographical data for a given IPv4 address.
@@ -176,19 +421,30 @@ impl GeoLookup {
             self.extract_country_data(&lookup, &mut geodata);
             self.extract_region_data(&lookup, &mut geodata);
             self.extract_city_data(&lookup, &mut geodata);
+            self.extract_location_data(&lookup, &mut geodata);
+        }
+        if let Some(asn_reader) = &self.asn_reader {
+            if let Ok(asn) = asn_reader.lookup::<Asn>(std::net::IpAddr::V4(*ip)) {
+                geodata.asn = asn.autonomous_system_number;
+                geodata.as_org = asn.autonomous_system_organization.map(ToString::to_string);
+            }
         }
         geodata
     }
 
-    /// Extracts country data from the lookup result and populates the `GeoData`.
+    /// Extracts continent and country data from the lookup result and populates the `GeoData`.
     ///
     /// # Arguments
     ///
     /// * `lookup`: The lookup result containing geographic information.
-    /// * `geodata`: The `GeoData` instance to populate with country information.
+    /// * `geodata`: The `GeoData` instance to populate with continent and country information.
     fn extract_country_data(&self, lookup: &City, geodata: &mut GeoData) {
+        if let Some(continent) = &lookup.continent {
+            geodata.continent_code = continent.code.map(ToString::to_string);
+        }
         if let Some(country) = &lookup.country {
             geodata.iso_code = country.iso_code.map(ToString::to_string);
+            geodata.is_in_european_union = country.is_in_european_union.unwrap_or(false);
             if let Some(country_names) = &country.names {
                 geodata.name = country_names.get("en").map(ToString::to_string);
             }
@@ -230,4 +486,23 @@ impl GeoLookup {
             }
         }
     }
+
+    /// Extracts coordinates, time zone and postal code from the lookup result and populates
+    /// the `GeoData`.
+    ///
+    /// # Arguments
+    ///
+    /// * `lookup`: The lookup result containing geographic information.
+    /// * `geodata`: The `GeoData` instance to populate with location information.
+    fn extract_location_data(&self, lookup: &City, geodata: &mut GeoData) {
+        if let Some(location) = &lookup.location {
+            geodata.latitude = location.latitude;
+            geodata.longitude = location.longitude;
+            geodata.accuracy_radius = location.accuracy_radius;
+            geodata.time_zone = location.time_zone.map(ToString::to_string);
+        }
+        if let Some(postal) = &lookup.postal {
+            geodata.postal_code = postal.code.map(ToString::to_string);
+        }
+    }
 }
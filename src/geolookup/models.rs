@@ -1,8 +1,13 @@
 use serde::Serialize;
 
+/// Mean radius of the Earth, in kilometers.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
 /// Contains geographical data related to a proxy.
 #[derive(Debug, Default, Clone, Serialize)]
 pub struct GeoData {
+    /// Continent code.
+    pub continent_code: Option<String>,
     /// ISO country code.
     pub iso_code: Option<String>,
     /// Country name.
@@ -13,4 +18,42 @@ pub struct GeoData {
     pub region_name: Option<String>,
     /// Name of the city.
     pub city_name: Option<String>,
+    /// Postal code of the location.
+    pub postal_code: Option<String>,
+    /// Approximate latitude of the location.
+    pub latitude: Option<f64>,
+    /// Approximate longitude of the location.
+    pub longitude: Option<f64>,
+    /// Radius in kilometers around the coordinates where the address is likely located.
+    pub accuracy_radius: Option<u16>,
+    /// IANA time zone of the location, e.g. `Europe/Paris`.
+    pub time_zone: Option<String>,
+    /// Indicates if the country is a member of the European Union; `false` if unknown.
+    pub is_in_european_union: bool,
+    /// Autonomous system number; requires the ASN database.
+    pub asn: Option<u32>,
+    /// Organisation owning the autonomous system; requires the ASN database.
+    pub as_org: Option<String>,
+}
+
+impl GeoData {
+    /// Calculates the great-circle distance between the location and a point.
+    ///
+    /// # Arguments
+    ///
+    /// * `latitude`: Latitude of the point, in degrees.
+    /// * `longitude`: Longitude of the point, in degrees.
+    ///
+    /// # Returns
+    ///
+    /// The distance in kilometers, or `None` if the coordinates of the location are unknown.
+    pub fn distance_km(&self, latitude: f64, longitude: f64) -> Option<f64> {
+        let (lat1, lon1) = (self.latitude?.to_radians(), self.longitude?.to_radians());
+        let (lat2, lon2) = (latitude.to_radians(), longitude.to_radians());
+
+        // Haversine formula
+        let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+            + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+        Some(2.0 * EARTH_RADIUS_KM * a.sqrt().asin())
+    }
 }
@@ -1,16 +1,18 @@
+mod error;
 pub mod fetcher;
 pub mod geolookup;
 pub mod negotiators;
 pub mod providers;
 pub mod proxy;
+pub mod resolver;
 pub mod validator;
 
-mod resolver;
-
+pub use error::{Error, Result};
 use fetcher::{Config, ProxyFetcher};
 use proxy::models::{Anonymity, Protocol, Proxy};
 use std::{
-    fs::File,
+    env::current_dir,
+    fs::{self, File},
     io::{BufReader, Lines},
 };
 use std::{io::BufRead, net::Ipv4Addr, path::PathBuf};
@@ -38,6 +40,27 @@ pub fn initialize_logging(log_level: log::LevelFilter) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Retrieves the data directory path for the application.
+///
+/// # Returns
+///
+/// A `PathBuf` representing the path to the data directory.
+pub(crate) fn data_dir() -> anyhow::Result<PathBuf> {
+    if let Some(base_dirs) = directories::BaseDirs::new() {
+        let mut dir = base_dirs.data_dir().to_path_buf();
+        dir.push(env!("CARGO_PKG_NAME"));
+
+        if !dir.is_dir() {
+            fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    } else {
+        #[cfg(feature = "log")]
+        log::warn!("Failed to get local data directory, using current directory instead");
+        Ok(current_dir().unwrap_or_default())
+    }
+}
+
 /// Represents a source of proxy servers, either from a file or a network fetcher.
 pub struct ProxySource {
     lines: Lines<BufReader<File>>,
@@ -54,12 +77,15 @@ impl ProxySource {
     /// # Returns
     ///
     /// A result containing the `ProxyFetcher` or an error if the operation fails.
-    pub async fn from_fetcher(config: Config) -> anyhow::Result<ProxyFetcher> {
+    pub async fn from_fetcher(config: Config) -> crate::Result<ProxyFetcher> {
         ProxyFetcher::gather(config).await
     }
 
     /// Creates a `ProxySource` from a specified file path.
     ///
+    /// Proxies read this way bypass the fetcher, so they get no geo lookup, country
+    /// filtering or deduplication; prefer `fetcher::Config::files`.
+    ///
     /// # Arguments
     ///
     /// * `filepath`: The path to the file containing proxy server information.
@@ -67,7 +93,8 @@ impl ProxySource {
     /// # Returns
     ///
     /// A result containing the `ProxySource` or an error if the operation fails.
-    pub fn from_file(filepath: PathBuf) -> anyhow::Result<Self> {
+    #[deprecated(note = "use `fetcher::Config::files` to read files through the fetcher")]
+    pub fn from_file(filepath: PathBuf) -> crate::Result<Self> {
         let file = File::open(filepath)?;
         let buffered_reader = BufReader::new(file);
         let lines = buffered_reader.lines();
@@ -7,6 +7,13 @@ use tokio::{
 };
 
 use super::NegotiatorTrait;
+use crate::{
+    proxy::models::{Protocol, Timings},
+    Error,
+};
+
+/// Maximum size of the proxy's response to the CONNECT request.
+const MAX_RESPONSE_SIZE: usize = 8192;
 
 /// A negotiator for HTTPS proxies.
 pub struct HttpsNegotiator;
@@ -17,14 +24,15 @@ impl HttpsNegotiator {
     /// # Arguments
     ///
     /// * `host`: The host to connect to through the proxy.
+    /// * `port`: The port to connect to.
     ///
     /// # Returns
     ///
     /// A `String` containing the raw bytes of the CONNECT request.
-    fn generate_connect_request(&self, host: &str) -> String {
+    fn generate_connect_request(&self, host: &str, port: u16) -> String {
         format!(
-            "CONNECT {}:443 HTTP/1.1\r\nHost: {}\r\nConnection: keep-alive\r\n\r\n",
-            host, host
+            "CONNECT {}:{} HTTP/1.1\r\nHost: {}:{}\r\nConnection: keep-alive\r\n\r\n",
+            host, port, host, port
         )
     }
 }
@@ -34,16 +42,21 @@ impl NegotiatorTrait for HttpsNegotiator {
     async fn negotiate(
         &self,
         stream: &mut TcpStream,
-        runtimes: &mut Vec<f64>,
+        timings: &mut Timings,
         proxy_host: &str,
         uri: &Uri,
-    ) -> anyhow::Result<()> {
+    ) -> crate::Result<()> {
         if let Some(host) = uri.host() {
-            let connect_request = self.generate_connect_request(host);
+            let connect_request =
+                self.generate_connect_request(host, uri.port_u16().unwrap_or(443));
 
             // Ensure the request uses HTTPS
-            if !uri.scheme().map_or(false, |s| s.as_str() == "https") {
-                anyhow::bail!("Scheme is empty or not https");
+            if uri.scheme_str() != Some("https") {
+                return Err(Error::negotiation(
+                    Protocol::Https,
+                    None,
+                    "Scheme is empty or not https",
+                ));
             }
 
             self.log_trace(
@@ -52,25 +65,55 @@ impl NegotiatorTrait for HttpsNegotiator {
             );
             let start_time = time::Instant::now();
             stream.write_all(connect_request.as_bytes()).await?;
-            runtimes.push(start_time.elapsed().as_secs_f64());
 
-            let mut buf = [0; 64];
-            stream.read_exact(&mut buf).await?;
+            // Read up to the end of the response headers, however short the response is
+            let mut buf = Vec::with_capacity(128);
+            let mut chunk = [0; 128];
+            while !buf.windows(4).any(|window| window == b"\r\n\r\n") {
+                let read = stream.read(&mut chunk).await?;
+                if read == 0 {
+                    return Err(Error::negotiation(
+                        Protocol::Https,
+                        None,
+                        "Connection closed before the response was complete",
+                    ));
+                }
+                buf.extend_from_slice(&chunk[..read]);
+                if buf.len() > MAX_RESPONSE_SIZE {
+                    return Err(Error::negotiation(
+                        Protocol::Https,
+                        None,
+                        "Response headers are too large",
+                    ));
+                }
+            }
 
             let mut header = [httparse::EMPTY_HEADER; 32];
             let mut response = httparse::Response::new(&mut header);
-            response.parse(&buf)?;
+            response
+                .parse(&buf)
+                .map_err(|e| Error::negotiation(Protocol::Https, None, e.to_string()))?;
 
-            let code = response.code.unwrap_or_default();
-            if code != 200 {
-                anyhow::bail!(
-                    "Got response {}: {}. Expecting 200 OK",
-                    code,
-                    response.reason.unwrap_or("Unknown reason")
-                );
+            match response.code.unwrap_or_default() {
+                200 => {}
+                407 => {
+                    return Err(Error::AuthRequired {
+                        protocol: Protocol::Https,
+                    })
+                }
+                code => {
+                    return Err(Error::negotiation(
+                        Protocol::Https,
+                        Some(code),
+                        format!(
+                            "{}, expecting 200 OK",
+                            response.reason.unwrap_or("Unknown reason")
+                        ),
+                    ))
+                }
             }
             self.log_trace(proxy_host, "Connection successfully established");
-            runtimes.push(start_time.elapsed().as_secs_f64());
+            timings.add_negotiate(start_time.elapsed().as_secs_f64());
         }
         Ok(())
     }
@@ -13,6 +13,8 @@ pub use socks4::Socks4Negotiator;
 pub use socks5::Socks5Negotiator;
 use tokio::net::TcpStream;
 
+use crate::proxy::models::Timings;
+
 /// Trait defining the negotiation behavior for different proxy types.
 #[async_trait]
 pub trait NegotiatorTrait {
@@ -21,6 +23,7 @@ pub trait NegotiatorTrait {
     /// # Arguments
     ///
     /// * `stream`: The TCP stream to negotiate.
+    /// * `timings`: The timings of the request, whose `negotiate` stage is filled in.
     /// * `proxy`: The proxy being used for the negotiation.
     /// * `uri`: The URI to be accessed through the proxy.
     ///
@@ -31,10 +34,10 @@ pub trait NegotiatorTrait {
     async fn negotiate(
         &self,
         stream: &mut TcpStream,
-        runtimes: &mut Vec<f64>,
+        timings: &mut Timings,
         proxy_host: &str,
         uri: &Uri,
-    ) -> anyhow::Result<()> {
+    ) -> crate::Result<()> {
         Ok(())
     }
 
@@ -12,6 +12,10 @@ use async_trait::async_trait;
 use hyper::Uri;
 
 use super::NegotiatorTrait;
+use crate::{
+    proxy::models::{Protocol, Timings},
+    Error,
+};
 
 /// A negotiator for SOCKS4 proxies.
 pub struct Socks4Negotiator;
@@ -23,6 +27,7 @@ impl NegotiatorTrait for Socks4Negotiator {
     /// # Arguments
     ///
     /// * `stream`: The TCP stream to negotiate.
+    /// * `timings`: The timings of the request, whose `negotiate` stage is filled in.
     /// * `proxy`: The proxy being used for the negotiation.
     /// * `_uri`: The URI to be accessed through the proxy (not used for SOCKS4).
     ///
@@ -32,49 +37,58 @@ impl NegotiatorTrait for Socks4Negotiator {
     async fn negotiate(
         &self,
         stream: &mut TcpStream,
-        runtimes: &mut Vec<f64>,
+        timings: &mut Timings,
         proxy_host: &str,
         _uri: &Uri,
-    ) -> anyhow::Result<()> {
+    ) -> crate::Result<()> {
         let parts = proxy_host.split(':').collect::<Vec<_>>();
 
         // Prepare the SOCKS4 connection request packet
         let data = (
             4u8,
             1u8,
-            parts[1].parse::<u16>()?,
-            parts[0].parse::<Ipv4Addr>()?.octets(),
+            parts[1].parse::<u16>().map_err(anyhow::Error::from)?,
+            parts[0]
+                .parse::<Ipv4Addr>()
+                .map_err(anyhow::Error::from)?
+                .octets(),
             0u8,
         );
         let mut cursor = Cursor::new(Vec::new());
         data.pack_to::<BigEndian, _>(&mut cursor)?;
         let packet = cursor.into_inner();
 
-        // Send the connection request to the SOCKS4 proxy
+        // Send the connection request to the SOCKS4 proxy and read its response
         let start_time = Instant::now();
         stream.write_all(&packet).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
-
-        // Read the response from the SOCKS4 proxy
         let mut response = [0u8; 8];
-        let start_time = Instant::now();
         stream.read_exact(&mut response).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
+        timings.add_negotiate(start_time.elapsed().as_secs_f64());
 
         // Validate the response
         let mut response_slice = &response[..];
         if response_slice.read_u8().await? != 0 {
-            anyhow::bail!("InvalidData: invalid response version");
-        }
-
-        match response_slice.read_u8().await? {
-            90 => {} // 90: Request granted
-            91 => anyhow::bail!("Other: Request rejected or failed"),
-            92 => anyhow::bail!("PermissionDenied: Request rejected because SOCKS server cannot connect to identd on the client"),
-            93 => anyhow::bail!("PermissionDenied: Request rejected because the client program and identd report different user IDs"),
-            code => anyhow::bail!("InvalidData: invalid response code: {}", code),
+            return Err(Error::negotiation(
+                Protocol::Socks4,
+                None,
+                "Invalid response version",
+            ));
         }
 
-        Ok(())
+        let code = response_slice.read_u8().await?;
+        let reason = match code {
+            90 => return Ok(()), // 90: Request granted
+            91 => "Request rejected or failed",
+            92 => "Request rejected because SOCKS server cannot connect to identd on the client",
+            93 => {
+                "Request rejected because the client program and identd report different user IDs"
+            }
+            _ => "Invalid response code",
+        };
+        Err(Error::negotiation(
+            Protocol::Socks4,
+            Some(code as u16),
+            reason,
+        ))
     }
 }
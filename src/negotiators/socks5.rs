@@ -10,6 +10,10 @@ use tokio::{
 };
 
 use super::NegotiatorTrait;
+use crate::{
+    proxy::models::{Protocol, Timings},
+    Error,
+};
 
 /// A negotiator for SOCKS5 proxies.
 pub struct Socks5Negotiator;
@@ -21,6 +25,7 @@ impl NegotiatorTrait for Socks5Negotiator {
     /// # Arguments
     ///
     /// * `stream`: The TCP stream to negotiate.
+    /// * `timings`: The timings of the request, whose `negotiate` stage is filled in.
     /// * `proxy`: The proxy being used for the negotiation.
     /// * `_uri`: The URI to be accessed through the proxy (not used for SOCKS5).
     ///
@@ -30,32 +35,41 @@ impl NegotiatorTrait for Socks5Negotiator {
     async fn negotiate(
         &self,
         stream: &mut TcpStream,
-        runtimes: &mut Vec<f64>,
+        timings: &mut Timings,
         proxy_host: &str,
         _uri: &hyper::Uri,
-    ) -> anyhow::Result<()> {
+    ) -> crate::Result<()> {
         // Prepare the initial SOCKS5 handshake packet
         let handshake_packet = [5, 1, 0]; // Version, number of methods, no authentication
 
+        // Each exchange is timed from the write until the whole reply is read
         let start_time = Instant::now();
         stream.write_all(&handshake_packet).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
 
         // Read the response from the SOCKS5 server
         let mut response_buf = [0; 2];
-        let start_time = Instant::now();
         stream.read_exact(&mut response_buf).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
+        timings.add_negotiate(start_time.elapsed().as_secs_f64());
 
         if response_buf[0] != 0x05 {
-            anyhow::bail!("InvalidData: invalid response version");
+            return Err(Error::negotiation(
+                Protocol::Socks5,
+                None,
+                "Invalid response version",
+            ));
         }
         if response_buf[1] == 0xff {
             // TODO: Support for SOCKS5 authentication
-            anyhow::bail!("PermissionDenied: authentication is required");
+            return Err(Error::AuthRequired {
+                protocol: Protocol::Socks5,
+            });
         }
         if response_buf[1] != 0x00 {
-            anyhow::bail!("InvalidData: invalid response data");
+            return Err(Error::negotiation(
+                Protocol::Socks5,
+                Some(response_buf[1] as u16),
+                "Unsupported authentication method",
+            ));
         }
         let parts = proxy_host.split(':').collect::<Vec<_>>();
 
@@ -65,8 +79,11 @@ impl NegotiatorTrait for Socks5Negotiator {
             1u8,
             0u8,
             1u8,
-            parts[0].parse::<Ipv4Addr>()?.octets(),
-            parts[1].parse::<u16>()?,
+            parts[0]
+                .parse::<Ipv4Addr>()
+                .map_err(anyhow::Error::from)?
+                .octets(),
+            parts[1].parse::<u16>().map_err(anyhow::Error::from)?,
         );
 
         let mut cursor = Cursor::new(Vec::new());
@@ -75,21 +92,42 @@ impl NegotiatorTrait for Socks5Negotiator {
 
         let start_time = Instant::now();
         stream.write_all(&connection_packet).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
 
         // Read the response for the connection request
         let mut response_buf = [0; 10];
-        let start_time = Instant::now();
         stream.read_exact(&mut response_buf).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
+        timings.add_negotiate(start_time.elapsed().as_secs_f64());
 
         if response_buf[0] != 0x05 {
-            anyhow::bail!("InvalidData: invalid response version");
+            return Err(Error::negotiation(
+                Protocol::Socks5,
+                None,
+                "Invalid response version",
+            ));
         }
         if response_buf[1] != 0x00 {
-            anyhow::bail!("InvalidData: invalid response data");
+            return Err(Error::negotiation(
+                Protocol::Socks5,
+                Some(response_buf[1] as u16),
+                socks5_reply(response_buf[1]),
+            ));
         }
 
         Ok(())
     }
 }
+
+/// Describes a SOCKS5 reply code (RFC 1928).
+fn socks5_reply(code: u8) -> &'static str {
+    match code {
+        0x01 => "General SOCKS server failure",
+        0x02 => "Connection not allowed by ruleset",
+        0x03 => "Network unreachable",
+        0x04 => "Host unreachable",
+        0x05 => "Connection refused",
+        0x06 => "TTL expired",
+        0x07 => "Command not supported",
+        0x08 => "Address type not supported",
+        _ => "Invalid response data",
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_dir;
+
+/// Validators and bookkeeping stored next to a cached source body.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// URL the body was fetched from.
+    pub url: String,
+    /// URL the body was served from after following redirects.
+    #[serde(default)]
+    pub final_url: Option<String>,
+    /// Value of the `ETag` header, sent back as `If-None-Match`.
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header, sent back as `If-Modified-Since`.
+    pub last_modified: Option<String>,
+    /// Time when the body was stored, in seconds since the Unix epoch.
+    pub fetched_on: f64,
+}
+
+/// On-disk cache of provider source bodies, keyed by URL.
+pub struct SourceCache {
+    dir: PathBuf, // Directory holding the cached bodies and their metadata.
+}
+
+impl SourceCache {
+    /// Creates a cache rooted at the given directory, creating it if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: The directory in which cached sources are stored.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the `SourceCache` or an error if the directory cannot be created.
+    pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+        if !dir.is_dir() {
+            fs::create_dir_all(&dir)?;
+        }
+        Ok(Self { dir })
+    }
+
+    /// Opens the cache inside the application data directory.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the `SourceCache` or an error if the directory cannot be created.
+    pub fn open() -> anyhow::Result<Self> {
+        let mut dir = data_dir()?;
+        dir.push("sources");
+        Self::new(dir)
+    }
+
+    /// Computes a stable file name for the given URL (64-bit FNV-1a).
+    fn key(url: &str) -> String {
+        let hash = url.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    fn body_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.txt", Self::key(url)))
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", Self::key(url)))
+    }
+
+    /// Returns the cache metadata for the given URL, if it has been cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The URL of the source.
+    ///
+    /// # Returns
+    ///
+    /// An optional `CacheEntry`; `None` if the URL is not cached or its body is missing.
+    pub fn entry(&self, url: &str) -> Option<CacheEntry> {
+        if !self.body_path(url).is_file() {
+            return None;
+        }
+        let content = fs::read_to_string(self.entry_path(url)).ok()?;
+        serde_json::from_str::<CacheEntry>(&content)
+            .ok()
+            .filter(|entry| entry.url == url)
+    }
+
+    /// Reads the cached body for the given URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The URL of the source.
+    ///
+    /// # Returns
+    ///
+    /// An optional `String` containing the cached body; `None` if the URL is not cached,
+    /// including when the stored files belong to another URL with the same key.
+    pub fn read(&self, url: &str) -> Option<String> {
+        self.entry(url)?;
+        fs::read_to_string(self.body_path(url)).ok()
+    }
+
+    /// Stores a body and its validators for the given URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The URL of the source.
+    /// * `final_url`: The URL the body was served from after following redirects.
+    /// * `etag`: The `ETag` header returned by the server (optional).
+    /// * `last_modified`: The `Last-Modified` header returned by the server (optional).
+    /// * `body`: The body to store.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating success or failure of the write.
+    pub fn write(
+        &self,
+        url: &str,
+        final_url: &str,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        body: &str,
+    ) -> anyhow::Result<()> {
+        let entry = CacheEntry {
+            url: url.to_string(),
+            final_url: Some(final_url.to_string()).filter(|final_url| final_url != url),
+            etag,
+            last_modified,
+            fetched_on: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or_default(),
+        };
+        write_replacing(&self.body_path(url), body)?;
+        write_replacing(&self.entry_path(url), &serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+}
+
+/// Writes a file through a temporary file renamed over it, so that readers and concurrent
+/// runs never see a partial file.
+fn write_replacing(path: &Path, content: &str) -> anyhow::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let written = fs::write(&tmp_path, content).and_then(|()| fs::rename(&tmp_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    Ok(written?)
+}
@@ -0,0 +1,429 @@
+use std::{
+    net::{Ipv4Addr, SocketAddrV4},
+    ops::Range,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hashbrown::HashMap;
+
+/// Rewrites a page so that the proxy addresses it hides become plain text.
+///
+/// Decoders are chained by a provider before extraction; each one replaces the
+/// obfuscated fragments it recognises and leaves the rest of the page untouched.
+pub trait Decoder: Send + Sync {
+    /// Decodes the obfuscated fragments of the given page.
+    ///
+    /// # Arguments
+    ///
+    /// * `html`: The page content.
+    ///
+    /// # Returns
+    ///
+    /// The page with the recognised fragments replaced by their decoded value.
+    fn decode(&self, html: &str) -> String;
+}
+
+/// Runs the given decoders over a page, in order.
+///
+/// # Arguments
+///
+/// * `html`: The page content.
+/// * `decoders`: The decoders to apply.
+///
+/// # Returns
+///
+/// The decoded page.
+pub fn decode(html: &str, decoders: &[Box<dyn Decoder>]) -> String {
+    decoders
+        .iter()
+        .fold(html.to_string(), |html, decoder| decoder.decode(&html))
+}
+
+/// Finds every `<ip>:<port>` address in the given text.
+///
+/// Addresses may be surrounded by markup or other text, but not by further digits or dots.
+///
+/// # Arguments
+///
+/// * `text`: The text to scan.
+///
+/// # Returns
+///
+/// A vector of the addresses found, in order of appearance.
+pub fn extract_addresses(text: &str) -> Vec<SocketAddrV4> {
+    let bytes = text.as_bytes();
+    let is_ip_char = |b: u8| b.is_ascii_digit() || b == b'.';
+    let mut addresses = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() || (i > 0 && is_ip_char(bytes[i - 1])) {
+            i += 1;
+            continue;
+        }
+
+        let ip_end = (i..bytes.len())
+            .find(|&j| !is_ip_char(bytes[j]))
+            .unwrap_or(bytes.len());
+        let port_start = ip_end + 1;
+        let port_end = (port_start..bytes.len())
+            .find(|&j| !bytes[j].is_ascii_digit())
+            .unwrap_or(bytes.len());
+
+        if bytes.get(ip_end) == Some(&b':') && port_end > port_start {
+            if let (Ok(ip), Ok(port)) = (
+                text[i..ip_end].parse::<Ipv4Addr>(),
+                text[port_start..port_end].parse::<u16>(),
+            ) {
+                addresses.push(SocketAddrV4::new(ip, port));
+                i = port_end;
+                continue;
+            }
+        }
+        i = ip_end;
+    }
+    addresses
+}
+
+/// Indicates if a decoded fragment looks like an address, port or part of one.
+fn is_address_like(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b == b'.' || b == b':')
+}
+
+/// Returns the byte ranges of the contents of every quoted string literal in the text.
+fn quoted_strings(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut ranges = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let quote = bytes[i];
+        if quote == b'"' || quote == b'\'' {
+            let end = bytes[i + 1..]
+                .iter()
+                .position(|&b| b == quote || b == b'\n')
+                .map(|len| i + 1 + len);
+            if let Some(end) = end.filter(|&end| bytes[end] == quote) {
+                ranges.push(i + 1..end);
+                i = end + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    ranges
+}
+
+/// Replaces the quoted string literals for which `decode` returns a value.
+fn replace_quoted<F>(html: &str, decode: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(html.len());
+    let mut last = 0;
+    for range in quoted_strings(html) {
+        if let Some(decoded) = decode(&html[range.clone()]) {
+            output.push_str(&html[last..range.start]);
+            output.push_str(&decoded);
+            last = range.end;
+        }
+    }
+    output.push_str(&html[last..]);
+    output
+}
+
+/// Decodes base64 string literals holding an address, e.g. `Base64.decode("MS4yLjMuNDo4MA==")`.
+pub struct Base64Decoder;
+
+impl Decoder for Base64Decoder {
+    fn decode(&self, html: &str) -> String {
+        replace_quoted(html, |literal| {
+            if literal.len() < 4 || !literal.len().is_multiple_of(4) {
+                return None;
+            }
+            let decoded = STANDARD.decode(literal).ok()?;
+            let decoded = String::from_utf8(decoded).ok()?;
+            is_address_like(&decoded).then_some(decoded)
+        })
+    }
+}
+
+/// Decodes hex encoded string literals holding an address, either as a plain hex string
+/// (`"312e322e332e343a3830"`) or as escape sequences (`"\x31\x2e\x32..."`).
+pub struct HexDecoder;
+
+impl HexDecoder {
+    fn decode_hex(literal: &str) -> Option<String> {
+        let digits = literal.replace("\\x", "");
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return None;
+        }
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<_>>>()?;
+        String::from_utf8(bytes).ok()
+    }
+}
+
+impl Decoder for HexDecoder {
+    fn decode(&self, html: &str) -> String {
+        replace_quoted(html, |literal| {
+            let decoded = Self::decode_hex(literal)?;
+            is_address_like(&decoded).then_some(decoded)
+        })
+    }
+}
+
+/// Evaluates the JavaScript variable tables used to hide ports behind arithmetic,
+/// e.g. `<script>document.write(":"+(a1b2^c3d4)+(e5f6^g7h8))</script>`.
+///
+/// Every `name=expression;` assignment found in the page's scripts is evaluated in order,
+/// then each script writing to the document is replaced by the text it would write.
+pub struct JsVariableDecoder;
+
+impl JsVariableDecoder {
+    /// Returns the byte ranges of every `<script>` element and of its contents.
+    fn scripts(html: &str) -> Vec<(Range<usize>, Range<usize>)> {
+        let lower = html.to_ascii_lowercase();
+        let mut scripts = vec![];
+        let mut offset = 0;
+
+        while let Some(start) = lower[offset..].find("<script").map(|i| i + offset) {
+            let Some(body_start) = lower[start..].find('>').map(|i| start + i + 1) else {
+                break;
+            };
+            let Some(body_end) = lower[body_start..]
+                .find("</script>")
+                .map(|i| body_start + i)
+            else {
+                break;
+            };
+            let end = body_end + "</script>".len();
+            scripts.push((start..end, body_start..body_end));
+            offset = end;
+        }
+        scripts
+    }
+
+    /// Collects the variables assigned in the given script.
+    fn collect_variables(script: &str, variables: &mut HashMap<String, f64>) {
+        for statement in script.split([';', '\n']) {
+            let statement = statement.trim().trim_start_matches("var ").trim();
+            let Some((name, expression)) = statement.split_once('=') else {
+                continue;
+            };
+            let name = name.trim();
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+            {
+                continue;
+            }
+            if let Some(value) = Expression::new(expression, variables).evaluate() {
+                variables.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    /// Computes the text written by the `document.write` calls of the given script.
+    fn written_text(script: &str, variables: &HashMap<String, f64>) -> Option<String> {
+        let mut output = String::new();
+        let mut found = false;
+        let mut rest = script;
+
+        while let Some(start) = rest.find("document.write(") {
+            let arguments = &rest[start + "document.write(".len()..];
+            let end = matching_paren(arguments)?;
+            for part in split_top_level(&arguments[..end], '+') {
+                let part = part.trim();
+                if let Some(literal) = part
+                    .strip_prefix('"')
+                    .and_then(|p| p.strip_suffix('"'))
+                    .or_else(|| part.strip_prefix('\'').and_then(|p| p.strip_suffix('\'')))
+                {
+                    output.push_str(literal);
+                } else {
+                    output.push_str(&Expression::new(part, variables).evaluate()?.to_string());
+                }
+            }
+            found = true;
+            rest = &arguments[end..];
+        }
+        found.then_some(output)
+    }
+}
+
+impl Decoder for JsVariableDecoder {
+    fn decode(&self, html: &str) -> String {
+        let scripts = Self::scripts(html);
+        let mut variables = HashMap::new();
+        for (_, body) in &scripts {
+            Self::collect_variables(&html[body.clone()], &mut variables);
+        }
+
+        let mut output = String::with_capacity(html.len());
+        let mut last = 0;
+        for (element, body) in scripts {
+            if let Some(text) = Self::written_text(&html[body], &variables) {
+                output.push_str(&html[last..element.start]);
+                output.push_str(&text);
+                last = element.end;
+            }
+        }
+        output.push_str(&html[last..]);
+        output
+    }
+}
+
+/// Returns the index of the parenthesis closing an already opened one.
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits the text on the given separator, ignoring separators nested in parentheses.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0usize;
+    let mut last = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                parts.push(&text[last..i]);
+                last = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[last..]);
+    parts
+}
+
+/// A small evaluator for the arithmetic used by obfuscation scripts.
+///
+/// Supports decimal and hex literals, variables, parentheses, unary minus and the
+/// `^ | & + - * / %` operators with JavaScript precedence and semantics: values are
+/// doubles, and bitwise operators work on their 32-bit integer conversions.
+struct Expression<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+    variables: &'a HashMap<String, f64>,
+}
+
+impl<'a> Expression<'a> {
+    fn new(text: &'a str, variables: &'a HashMap<String, f64>) -> Self {
+        let mut tokens = vec![];
+        let mut start = None;
+        for (i, c) in text.char_indices() {
+            // Dots belong to decimal literals
+            if c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.' {
+                start.get_or_insert(i);
+                continue;
+            }
+            if let Some(s) = start.take() {
+                tokens.push(&text[s..i]);
+            }
+            if !c.is_whitespace() {
+                tokens.push(&text[i..i + c.len_utf8()]);
+            }
+        }
+        if let Some(s) = start {
+            tokens.push(&text[s..]);
+        }
+
+        Self {
+            tokens,
+            position: 0,
+            variables,
+        }
+    }
+
+    fn evaluate(mut self) -> Option<f64> {
+        let value = self.binary(0)?;
+        // Divisions by zero make no sensible text either
+        (self.position == self.tokens.len() && value.is_finite()).then_some(value)
+    }
+
+    /// Converts a value the way JavaScript bitwise operators do (`ToInt32`).
+    fn to_int32(value: f64) -> i32 {
+        if !value.is_finite() {
+            return 0;
+        }
+        value.trunc().rem_euclid(4_294_967_296.0) as u32 as i32
+    }
+
+    fn precedence(operator: &str) -> Option<u8> {
+        match operator {
+            "|" => Some(1),
+            "^" => Some(2),
+            "&" => Some(3),
+            "+" | "-" => Some(4),
+            "*" | "/" | "%" => Some(5),
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, min_precedence: u8) -> Option<f64> {
+        let mut left = self.unary()?;
+        while let Some(&operator) = self.tokens.get(self.position) {
+            let Some(precedence) = Self::precedence(operator).filter(|&p| p > min_precedence)
+            else {
+                break;
+            };
+            self.position += 1;
+            let right = self.binary(precedence)?;
+            let (left_int, right_int) = (Self::to_int32(left), Self::to_int32(right));
+            left = match operator {
+                "|" => (left_int | right_int) as f64,
+                "^" => (left_int ^ right_int) as f64,
+                "&" => (left_int & right_int) as f64,
+                "+" => left + right,
+                "-" => left - right,
+                "*" => left * right,
+                "/" => left / right,
+                _ => left % right,
+            };
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        let token = *self.tokens.get(self.position)?;
+        self.position += 1;
+        match token {
+            "-" => self.unary().map(|value| -value),
+            "+" => self.unary(),
+            "(" => {
+                let value = self.binary(0)?;
+                (self.tokens.get(self.position) == Some(&")")).then(|| {
+                    self.position += 1;
+                    value
+                })
+            }
+            token => {
+                if let Some(hex) = token.strip_prefix("0x") {
+                    u64::from_str_radix(hex, 16).ok().map(|value| value as f64)
+                } else if token.starts_with(|c: char| c.is_ascii_digit()) {
+                    token.parse().ok()
+                } else {
+                    self.variables.get(token).copied()
+                }
+            }
+        }
+    }
+}
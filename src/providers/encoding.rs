@@ -0,0 +1,104 @@
+use std::io::Read;
+
+use brotli::Decompressor;
+use encoding_rs::{Encoding, UTF_8};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+
+/// Value sent in the `Accept-Encoding` header of source requests.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// Maximum size of a decompressed body, so a small compressed response cannot exhaust memory.
+pub const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Number of bytes read from a decoder; one more than allowed, to detect oversized bodies.
+const READ_LIMIT: u64 = MAX_DECOMPRESSED_SIZE as u64 + 1;
+
+/// Reverses the content codings listed in a `Content-Encoding` header.
+///
+/// Codings are listed in the order they were applied, so they are undone from last to first.
+///
+/// # Arguments
+///
+/// * `body`: The raw response body.
+/// * `content_encoding`: The value of the `Content-Encoding` header (optional).
+///
+/// # Returns
+///
+/// A result containing the decompressed body, or an error for unsupported or corrupted data
+/// and for bodies decompressing to more than `MAX_DECOMPRESSED_SIZE` bytes.
+pub fn decompress(mut body: Vec<u8>, content_encoding: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    let Some(content_encoding) = content_encoding else {
+        return Ok(body);
+    };
+
+    for coding in content_encoding.rsplit(',') {
+        let coding = coding.trim().to_ascii_lowercase();
+        let mut decoded = Vec::new();
+        match coding.as_str() {
+            "" | "identity" => continue,
+            "gzip" | "x-gzip" => {
+                GzDecoder::new(body.as_slice())
+                    .take(READ_LIMIT)
+                    .read_to_end(&mut decoded)?;
+            }
+            "deflate" => {
+                // Some servers send a raw deflate stream instead of the zlib wrapped one
+                if ZlibDecoder::new(body.as_slice())
+                    .take(READ_LIMIT)
+                    .read_to_end(&mut decoded)
+                    .is_err()
+                {
+                    decoded.clear();
+                    DeflateDecoder::new(body.as_slice())
+                        .take(READ_LIMIT)
+                        .read_to_end(&mut decoded)?;
+                }
+            }
+            "br" => {
+                Decompressor::new(body.as_slice(), 4096)
+                    .take(READ_LIMIT)
+                    .read_to_end(&mut decoded)?;
+            }
+            coding => anyhow::bail!("Unsupported content encoding: {}", coding),
+        }
+        if decoded.len() > MAX_DECOMPRESSED_SIZE {
+            anyhow::bail!(
+                "Decompressed body exceeds {} bytes ({})",
+                MAX_DECOMPRESSED_SIZE,
+                coding
+            );
+        }
+        body = decoded;
+    }
+    Ok(body)
+}
+
+/// Decodes a body into text using the charset declared in a `Content-Type` header.
+///
+/// A byte order mark takes precedence over the declared charset; without either, the
+/// body is decoded as UTF-8 with invalid sequences replaced.
+///
+/// # Arguments
+///
+/// * `body`: The decompressed response body.
+/// * `content_type`: The value of the `Content-Type` header (optional).
+///
+/// # Returns
+///
+/// The decoded text.
+pub fn decode_charset(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(|content_type| {
+            content_type.split(';').skip(1).find_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("charset")
+                    .then(|| value.trim().trim_matches('"'))
+            })
+        })
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
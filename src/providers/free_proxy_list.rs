@@ -7,18 +7,23 @@ pub struct FreeProxyListProvider;
 
 #[async_trait]
 impl IProxyTrait for FreeProxyListProvider {
+    /// Returns the name identifying the provider.
+    fn name(&self) -> &'static str {
+        "free-proxy-list"
+    }
+
     /// Returns a list of sources from which proxies can be fetched.
     ///
     /// # Returns
     ///
-    /// A vector of `Source` objects representing the proxy sources.
-    fn sources(&self) -> Vec<Source> {
-        vec![
-            Source::http("https://www.sslproxies.org/"),
-            Source::http("https://free-proxy-list.net/uk-proxy.html"),
-            Source::http("https://www.us-proxy.org/"),
-            Source::http("https://free-proxy-list.net/"),
-            Source::socks("https://socks-proxy.net/"),
-        ]
+    /// A result containing the `Source` objects representing the proxy sources.
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        Ok(vec![
+            Source::http("https://www.sslproxies.org/")?,
+            Source::http("https://free-proxy-list.net/uk-proxy.html")?,
+            Source::http("https://www.us-proxy.org/")?,
+            Source::http("https://free-proxy-list.net/")?,
+            Source::socks("https://socks-proxy.net/")?,
+        ])
     }
 }
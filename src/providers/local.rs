@@ -0,0 +1,160 @@
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use http_body_util::Empty;
+use hyper::body::Bytes;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use tokio::io::AsyncReadExt;
+use url::Url;
+
+use super::{
+    encoding,
+    models::{FetchOptions, Source, SourceContent},
+    IProxyTrait,
+};
+
+/// A provider reading proxy lists from local files, directories, glob patterns or stdin.
+pub struct LocalProvider {
+    paths: Vec<PathBuf>, // Paths, directories or glob patterns; `-` stands for stdin.
+}
+
+impl LocalProvider {
+    /// Creates a new `LocalProvider` for the given paths.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths`: Files, directories (every file directly inside is read), glob patterns,
+    ///   or `-` for the standard input.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `LocalProvider`.
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self { paths }
+    }
+
+    /// Expands a path, directory or glob pattern into the files it designates.
+    fn expand(&self, path: &Path) -> Vec<PathBuf> {
+        if path.is_file() {
+            return vec![path.to_path_buf()];
+        }
+
+        let mut files = if path.is_dir() {
+            fs::read_dir(path)
+                .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+                .unwrap_or_default()
+        } else {
+            glob::glob(&path.to_string_lossy())
+                .map(|paths| paths.flatten().collect())
+                .unwrap_or_else(|_e| {
+                    #[cfg(feature = "log")]
+                    log::error!("{}: Invalid glob pattern: {}", path.display(), _e);
+                    vec![]
+                })
+        };
+        files.retain(|file: &PathBuf| file.is_file());
+        files.sort();
+
+        #[cfg(feature = "log")]
+        if files.is_empty() {
+            log::warn!(
+                "{}: No such file, directory or matching files",
+                path.display()
+            );
+        }
+        files
+    }
+}
+
+#[async_trait]
+impl IProxyTrait for LocalProvider {
+    /// Returns the name identifying the provider.
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    /// Returns a source for each file designated by the configured paths.
+    ///
+    /// Files that cannot be turned into a source are logged and skipped.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the `Source` objects representing the proxy sources.
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        let mut sources = vec![];
+        let mut with_stdin = false;
+        for path in &self.paths {
+            if path.as_os_str() == "-" {
+                if !with_stdin {
+                    sources.push(Source::stdin()?);
+                    with_stdin = true;
+                }
+                continue;
+            }
+            for file in self.expand(path) {
+                match Source::file(&file) {
+                    Ok(source) if !sources.iter().any(|s: &Source| s.url == source.url) => {
+                        sources.push(source)
+                    }
+                    Ok(_) => {}
+                    Err(_e) => {
+                        #[cfg(feature = "log")]
+                        log::error!("{}: {}", file.display(), _e);
+                    }
+                }
+            }
+        }
+        Ok(sources)
+    }
+
+    /// Reads the content of a local file or of the standard input.
+    ///
+    /// # Arguments
+    ///
+    /// * `_client`: Unused, local sources are not requested over the network.
+    /// * `url`: The `file:` URL of the file, or `stdin:`.
+    /// * `_timeout`: Unused, reading local sources is not time limited.
+    /// * `_options`: Unused, local sources are neither cached nor affected by offline mode.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the content or an error if it cannot be read.
+    async fn fetch(
+        &self,
+        _client: Arc<Client<HttpsConnector<HttpConnector>, Empty<Bytes>>>,
+        url: &str,
+        _timeout: Duration,
+        _options: &FetchOptions,
+    ) -> anyhow::Result<SourceContent> {
+        let parsed = Url::parse(url)?;
+        let body = match parsed.scheme() {
+            "file" => {
+                let path = parsed
+                    .to_file_path()
+                    .map_err(|_| anyhow::anyhow!("Invalid file URL: {}", url))?;
+                tokio::fs::read(path).await?
+            }
+            "stdin" => {
+                let mut body = vec![];
+                tokio::io::stdin().read_to_end(&mut body).await?;
+                body
+            }
+            scheme => anyhow::bail!("Unsupported local source scheme: {}", scheme),
+        };
+
+        Ok(SourceContent {
+            url: url.to_string(),
+            status: None,
+            bytes: body.len(),
+            from_cache: false,
+            body: Cow::Owned(encoding::decode_charset(&body, None)),
+        })
+    }
+}
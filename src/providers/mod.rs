@@ -1,125 +1,336 @@
-use std::{borrow::Cow, collections::VecDeque, net::Ipv4Addr, sync::Arc, time::Duration};
+use std::{borrow::Cow, net::Ipv4Addr, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
+use cache::CacheEntry;
+use decoders::Decoder;
 use fake::{faker::internet::en::UserAgent, Fake};
+use hashbrown::HashSet;
 use http_body_util::{BodyExt, Empty};
-use hyper::{body::Bytes, Request};
+use hyper::{body::Bytes, Request, StatusCode};
 use hyper_tls::HttpsConnector;
 use hyper_util::client::legacy::{connect::HttpConnector, Client};
-use models::Source;
+use models::{FetchOptions, Source, SourceContent};
 use tokio::time;
+use url::Url;
 
 use crate::proxy::models::{Protocol, Proxy};
 
+pub mod cache;
+pub mod decoders;
+pub mod encoding;
 mod free_proxy_list;
 mod github;
+mod local;
 pub mod models;
 mod proxyscrape;
 
 pub use free_proxy_list::FreeProxyListProvider;
 pub use github::GithubRepoProvider;
+pub use local::LocalProvider;
 pub use proxyscrape::ProxyscrapeProvider;
 
+/// Outcome of a request made to a source.
+enum Fetched {
+    /// The server returned a fresh body.
+    Content {
+        url: String,
+        status: u16,
+        bytes: usize,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        body: String,
+    },
+    /// The server confirmed the cached body is still up to date.
+    NotModified { url: String },
+}
+
+/// Requests the given URL, following redirects up to the configured limit.
+///
+/// Relative `Location` headers are resolved against the URL that returned them, and only
+/// the body of the final response is kept. The cached validators only apply to the
+/// requested URL, so redirect targets are requested unconditionally.
+///
+/// # Arguments
+///
+/// * `client`: The HTTP client used for making requests.
+/// * `url`: The URL to request.
+/// * `timeout`: The duration to wait before timing out the request.
+/// * `max_redirects`: Maximum number of redirects to follow.
+/// * `entry`: Cached validators used to make the request conditional (optional).
+///
+/// # Returns
+///
+/// A result containing the fetched content or an error if the request fails.
+async fn request(
+    client: Arc<Client<HttpsConnector<HttpConnector>, Empty<Bytes>>>,
+    url: &str,
+    timeout: Duration,
+    max_redirects: usize,
+    entry: Option<&CacheEntry>,
+) -> anyhow::Result<Fetched> {
+    let user_agent = UserAgent().fake::<&str>(); // Generate a fake user agent
+    let mut url = Url::parse(url)?;
+    let mut previous_url: Option<Url> = None;
+    let mut visited = HashSet::new();
+
+    loop {
+        visited.insert(url.clone());
+
+        let mut req = Request::builder()
+            .uri(url.as_str())
+            .header(hyper::header::USER_AGENT, user_agent)
+            .header(hyper::header::ACCEPT_ENCODING, encoding::ACCEPT_ENCODING);
+
+        if let Some(previous_url) = &previous_url {
+            req = req.header(hyper::header::REFERER, previous_url.as_str()); // Set the referer if available
+        }
+
+        // Make the request for the original URL conditional on the cached validators
+        if let Some(entry) = entry.filter(|_| previous_url.is_none()) {
+            if let Some(etag) = &entry.etag {
+                req = req.header(hyper::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                req = req.header(hyper::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        // Send the request and await the response with a timeout
+        let mut response =
+            time::timeout(timeout, client.request(req.body(Empty::<Bytes>::new())?)).await??;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified { url: url.into() });
+        }
+
+        // Handle possible redirects
+        if response.status().is_redirection() {
+            let Some(location) = response.headers().get(hyper::header::LOCATION) else {
+                anyhow::bail!("Got {} without a Location header", response.status());
+            };
+            let next_url = url.join(location.to_str()?)?;
+
+            if visited.contains(&next_url) {
+                anyhow::bail!("Redirect loop detected at {}", next_url);
+            }
+            if visited.len() > max_redirects {
+                anyhow::bail!("Too many redirects (limit is {})", max_redirects);
+            }
+            previous_url = Some(std::mem::replace(&mut url, next_url));
+            continue;
+        }
+
+        if !response.status().is_success() {
+            anyhow::bail!("Unexpected response status: {}", response.status());
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string)
+        };
+        let etag = header(hyper::header::ETAG);
+        let last_modified = header(hyper::header::LAST_MODIFIED);
+        let content_encoding = header(hyper::header::CONTENT_ENCODING);
+        let content_type = header(hyper::header::CONTENT_TYPE);
+
+        // Read the response frames
+        let mut body = Vec::new();
+        while let Some(next) = response.frame().await {
+            let frame = next?;
+            if let Some(chunk) = frame.data_ref() {
+                body.extend_from_slice(chunk); // Append chunk to body
+            }
+        }
+
+        // Undo the compression, then decode the declared charset
+        let bytes = body.len();
+        let body = encoding::decompress(body, content_encoding.as_deref())?;
+        return Ok(Fetched::Content {
+            url: url.into(),
+            status: response.status().as_u16(),
+            bytes,
+            etag,
+            last_modified,
+            body: encoding::decode_charset(&body, content_type.as_deref()),
+        });
+    }
+}
+
 /// Trait defining the behavior of proxy providers.
 #[async_trait]
 pub trait IProxyTrait {
+    /// Returns the name identifying the provider in proxy attributions and reports.
+    ///
+    /// # Returns
+    ///
+    /// The provider name; defaults to the name of the implementing type.
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
     /// Returns a list of sources from which proxies can be fetched.
     ///
     /// # Returns
     ///
-    /// A vector of `Source` objects representing the proxy sources.
-    fn sources(&self) -> Vec<Source>;
+    /// A result containing the `Source` objects representing the proxy sources, or an error
+    /// if one of them is invalid.
+    fn sources(&self) -> anyhow::Result<Vec<Source>>;
+
+    /// Returns the decoders applied to fetched content before proxies are extracted.
+    ///
+    /// Override this for sites hiding addresses behind base64, hex or script tricks.
+    ///
+    /// # Returns
+    ///
+    /// A vector of decoders, applied in order.
+    fn decoders(&self) -> Vec<Box<dyn Decoder>> {
+        vec![]
+    }
 
     /// Fetches the HTML content from the specified URL.
     ///
-    /// This method handles redirects and accumulates the HTML content from all frames.
+    /// This method follows redirects and returns the content of the final response along
+    /// with the URL it was served from. When a cache is configured, the request is made
+    /// conditional on the cached validators and the cached body is reused on
+    /// `304 Not Modified` or when the request fails.
     ///
     /// # Arguments
     ///
     /// * `client`: The HTTP client used for making requests.
     /// * `url`: The URL from which to fetch the HTML content.
     /// * `timeout`: The duration to wait before timing out the request.
+    /// * `options`: Cache, offline and redirect settings for the request.
     ///
     /// # Returns
     ///
-    /// A result containing the parsed HTML document or an error if the fetch fails.
+    /// A result containing the fetched content or an error if the fetch fails.
     async fn fetch(
         &self,
         client: Arc<Client<HttpsConnector<HttpConnector>, Empty<Bytes>>>,
         url: &str,
         timeout: Duration,
-    ) -> anyhow::Result<Cow<'static, str>> {
-        let mut urls = VecDeque::new();
-        urls.push_back((url.to_string(), None)); // Initialize with the first URL
-
-        let user_agent = UserAgent().fake::<&str>(); // Generate a fake user agent
-        let mut content = String::new(); // To accumulate HTML content
+        options: &FetchOptions,
+    ) -> anyhow::Result<SourceContent> {
+        let cache = options.cache.as_deref();
+        let entry = cache.and_then(|cache| cache.entry(url));
+        let from_cache = |entry: Option<CacheEntry>| {
+            let body = cache.and_then(|cache| cache.read(url))?;
+            Some(SourceContent {
+                url: entry
+                    .and_then(|entry| entry.final_url)
+                    .unwrap_or_else(|| url.to_string()),
+                status: None,
+                bytes: 0,
+                from_cache: true,
+                body: Cow::Owned(body),
+            })
+        };
 
-        while let Some((url, previous_url)) = urls.pop_front() {
-            let mut req = Request::builder()
-                .uri(&url)
-                .header(hyper::header::USER_AGENT, user_agent);
+        if options.offline {
+            return from_cache(entry)
+                .ok_or_else(|| anyhow::anyhow!("Source is not cached, skipped in offline mode"));
+        }
 
-            if let Some(previous_url) = previous_url {
-                req = req.header(hyper::header::REFERER, previous_url); // Set the referer if available
+        match request(client, url, timeout, options.max_redirects, entry.as_ref()).await {
+            Ok(Fetched::Content {
+                url: final_url,
+                status,
+                bytes,
+                etag,
+                last_modified,
+                body,
+            }) => {
+                if let Some(cache) = cache {
+                    if let Err(e) = cache.write(url, &final_url, etag, last_modified, &body) {
+                        #[cfg(feature = "log")]
+                        log::warn!("{}: Failed to cache source: {}", url, e);
+                    }
+                }
+                Ok(SourceContent {
+                    url: final_url,
+                    status: Some(status),
+                    bytes,
+                    from_cache: false,
+                    body: Cow::Owned(body),
+                })
             }
-
-            // Send the request and await the response with a timeout
-            let mut response =
-                time::timeout(timeout, client.request(req.body(Empty::<Bytes>::new())?)).await??;
-
-            // Handle possible redirects
-            if let Some(redirect) = response.headers().get(hyper::header::LOCATION) {
-                let redirect = redirect.to_str()?;
-                urls.push_back((redirect.to_string(), Some(url))); // Add redirect URL to the queue
-                continue;
+            Ok(Fetched::NotModified { url: final_url }) => {
+                let content = from_cache(entry).ok_or_else(|| {
+                    anyhow::anyhow!("Got 304 Not Modified but the cache is empty")
+                })?;
+                Ok(SourceContent {
+                    url: final_url,
+                    status: Some(StatusCode::NOT_MODIFIED.as_u16()),
+                    ..content
+                })
             }
-
-            // Read the response frames
-            while let Some(next) = response.frame().await {
-                let frame = next?;
-                if let Some(chunk) = frame.data_ref() {
-                    content.push_str(&String::from_utf8_lossy(chunk)); // Append chunk to content
+            Err(e) if entry.is_some() => match from_cache(entry) {
+                Some(content) => {
+                    #[cfg(feature = "log")]
+                    log::warn!("{}: {}, using cached copy", url, e);
+                    Ok(content)
                 }
-            }
+                None => Err(e),
+            },
+            Err(e) => Err(e),
         }
-        Ok(Cow::Owned(content))
     }
 
     /// Scrapes proxy information from the fetched HTML content.
     ///
+    /// Without decoders, each line holding an `<ip>:<port>` address yields a proxy. With
+    /// decoders, the content is first run through them, then every `<ip>:<port>` address
+    /// found in the decoded markup is collected.
+    ///
     /// # Arguments
     ///
     /// * `html`: The HTML document containing proxy information.
-    /// * `tx`: The channel to send found proxies.
-    /// * `counter`: A counter to track the number of proxies found.
     /// * `default_types`: Default protocol types for the proxies.
     ///
     /// # Returns
     ///
-    /// A result indicating success or failure of the scraping operation.
+    /// A result containing the proxies found in the document.
     async fn scrape(
         &self,
         html: Cow<'static, str>,
-        tx: kanal::AsyncSender<Proxy>,
         default_types: Vec<Protocol>,
-    ) -> anyhow::Result<()> {
-        for line in html.lines() {
-            let mut parts = line.trim().split(':');
-            if let (Some(ip_str), Some(port_str)) = (parts.next(), parts.next()) {
-                if let (Ok(ip), Ok(port)) = (ip_str.parse::<Ipv4Addr>(), port_str.parse::<u16>()) {
-                    let proxy = Proxy {
-                        ip,
-                        port,
-                        expected_types: default_types.clone(),
-                        ..Default::default()
-                    };
-                    if tx.send(proxy).await.is_err() {
-                        break;
+    ) -> anyhow::Result<Vec<Proxy>> {
+        let decoders = self.decoders();
+        if decoders.is_empty() {
+            let mut proxies = vec![];
+            for line in html.lines() {
+                let mut parts = line.trim().split(':');
+                if let (Some(ip_str), Some(port_str)) = (parts.next(), parts.next()) {
+                    if let (Ok(ip), Ok(port)) =
+                        (ip_str.parse::<Ipv4Addr>(), port_str.parse::<u16>())
+                    {
+                        proxies.push(Proxy {
+                            ip,
+                            port,
+                            expected_types: default_types.clone(),
+                            ..Default::default()
+                        });
                     }
                 }
             }
+            return Ok(proxies);
         }
-        Ok(())
+
+        // Decoded listings keep their markup around the addresses
+        let html = decoders::decode(&html, &decoders);
+        let proxies = decoders::extract_addresses(&html)
+            .into_iter()
+            .map(|address| Proxy {
+                ip: *address.ip(),
+                port: address.port(),
+                expected_types: default_types.clone(),
+                ..Default::default()
+            })
+            .collect();
+        Ok(proxies)
     }
 }
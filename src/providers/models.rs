@@ -1,14 +1,117 @@
-use std::{str::FromStr, time::Duration};
+use std::{borrow::Cow, ops::RangeInclusive, path::Path, sync::Arc, time::Duration};
 
-use hyper::Uri;
+use url::Url;
 
+use super::cache::SourceCache;
 use crate::proxy::models::{Anonymity, Protocol};
 
+/// Options controlling how a source is fetched.
+#[derive(Clone)]
+pub struct FetchOptions {
+    /// Cache used to store source bodies and revalidate them (optional).
+    pub cache: Option<Arc<SourceCache>>,
+    /// Only read sources from the cache; never touch the network.
+    pub offline: bool,
+    /// Maximum number of redirects to follow before giving up.
+    pub max_redirects: usize,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            cache: None,
+            offline: false,
+            max_redirects: 10,
+        }
+    }
+}
+
+/// Content fetched from a source.
+#[derive(Debug, Clone)]
+pub struct SourceContent {
+    /// URL the content was served from after following redirects.
+    pub url: String,
+    /// HTTP status of the final response; `None` if no request was made.
+    pub status: Option<u16>,
+    /// Number of body bytes received from the source, before decompression.
+    pub bytes: usize,
+    /// Indicates if the body was read from the local cache.
+    pub from_cache: bool,
+    /// Body of the final response.
+    pub body: Cow<'static, str>,
+}
+
+/// Maximum number of pages walked when a pagination has no last page.
+const MAX_PAGES: usize = 100;
+
+/// Describes how a source spreads its results across numbered pages.
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    /// URL template in which `{page}` is replaced with the page number.
+    pub template: String,
+    /// Number of the first page.
+    pub start: usize,
+    /// Number of the last page; if `None`, walk until a page yields no new proxies.
+    pub end: Option<usize>,
+}
+
+impl Pagination {
+    /// Creates a pagination over a fixed range of pages.
+    ///
+    /// # Arguments
+    ///
+    /// * `template`: The URL template, containing a `{page}` placeholder.
+    /// * `pages`: The range of page numbers to fetch.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `Pagination`.
+    pub fn range(template: &str, pages: RangeInclusive<usize>) -> Self {
+        Self {
+            template: template.to_string(),
+            start: *pages.start(),
+            end: Some(*pages.end()),
+        }
+    }
+
+    /// Creates a pagination that walks pages until one yields no new proxies.
+    ///
+    /// # Arguments
+    ///
+    /// * `template`: The URL template, containing a `{page}` placeholder.
+    /// * `start`: The number of the first page.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `Pagination`.
+    pub fn until_empty(template: &str, start: usize) -> Self {
+        Self {
+            template: template.to_string(),
+            start,
+            end: None,
+        }
+    }
+
+    /// Builds the URL of the given page.
+    pub fn url(&self, page: usize) -> String {
+        self.template.replace("{page}", &page.to_string())
+    }
+
+    /// Returns the URLs of every page, in order.
+    ///
+    /// Open-ended paginations are capped at 100 pages.
+    pub fn urls(&self) -> Vec<String> {
+        let end = self.end.unwrap_or(self.start.saturating_add(MAX_PAGES - 1));
+        (self.start..=end).map(|page| self.url(page)).collect()
+    }
+}
+
 /// Represents a source of proxy information, such as a URL and default protocol types.
 pub struct Source {
-    pub url: Uri,                     // URL of the proxy source.
-    pub default_types: Vec<Protocol>, // Default protocol types for the source.
-    pub timeout: Duration,            // Time before giving up on a request.
+    pub url: Url,                       // URL of the proxy source (first page if paginated).
+    pub default_types: Vec<Protocol>,   // Default protocol types for the source.
+    pub timeout: Option<Duration>,      // Overrides the fetcher's request timeout (optional).
+    pub pagination: Option<Pagination>, // How results are spread across pages (optional).
 }
 
 impl Source {
@@ -21,8 +124,8 @@ impl Source {
     ///
     /// # Returns
     ///
-    /// A new instance of `Source`.
-    pub fn new(url: &str, types: Vec<Protocol>) -> Self {
+    /// A result containing the new `Source`, or an error if the URL is invalid.
+    pub fn new(url: &str, types: Vec<Protocol>) -> anyhow::Result<Self> {
         let types = if types.is_empty() {
             vec![
                 Protocol::Http(Anonymity::Unknown),
@@ -36,10 +139,39 @@ impl Source {
             types
         };
 
-        Self {
-            url: Uri::from_str(url).unwrap(),
+        Ok(Self {
+            url: Url::parse(url).map_err(|e| anyhow::anyhow!("{}: Invalid URL: {}", url, e))?,
             default_types: types,
-            timeout: Duration::from_secs(3),
+            timeout: None,
+            pagination: None,
+        })
+    }
+
+    /// Spreads the source across the pages described by `pagination`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pagination`: The pages to walk; the first page replaces the source URL.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the updated `Source`, or an error if the first page URL is invalid.
+    pub fn paginated(mut self, pagination: Pagination) -> anyhow::Result<Self> {
+        let url = pagination.url(pagination.start);
+        self.url = Url::parse(&url).map_err(|e| anyhow::anyhow!("{}: Invalid URL: {}", url, e))?;
+        self.pagination = Some(pagination);
+        Ok(self)
+    }
+
+    /// Returns the URLs to fetch for this source, in order.
+    ///
+    /// # Returns
+    ///
+    /// The page URLs if the source is paginated, otherwise just the source URL.
+    pub fn urls(&self) -> Vec<String> {
+        match &self.pagination {
+            Some(pagination) => pagination.urls(),
+            None => vec![self.url.to_string()],
         }
     }
 
@@ -51,11 +183,37 @@ impl Source {
     ///
     /// # Returns
     ///
-    /// A new instance of `Source` with common protocols.
-    pub fn all(url: &str) -> Self {
+    /// A result containing the `Source` with common protocols, or an error if the URL is
+    /// invalid.
+    pub fn all(url: &str) -> anyhow::Result<Self> {
         Self::new(url, vec![])
     }
 
+    /// Creates a `Source` reading proxies from a local file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file; relative paths are resolved against the current directory.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the `Source` with common protocols, or an error if the path is invalid.
+    pub fn file(path: &Path) -> anyhow::Result<Self> {
+        let path = path.canonicalize()?;
+        let url = Url::from_file_path(&path)
+            .map_err(|_| anyhow::anyhow!("Invalid file path: {}", path.display()))?;
+        Self::all(url.as_str())
+    }
+
+    /// Creates a `Source` reading proxies from the standard input.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the `Source` with common protocols.
+    pub fn stdin() -> anyhow::Result<Self> {
+        Self::all("stdin:")
+    }
+
     /// Creates a `Source` with default types for HTTP protocols.
     ///
     /// # Arguments
@@ -64,8 +222,9 @@ impl Source {
     ///
     /// # Returns
     ///
-    /// A new instance of `Source` with HTTP protocol types.
-    pub fn http(url: &str) -> Self {
+    /// A result containing the `Source` with HTTP protocol types, or an error if the URL is
+    /// invalid.
+    pub fn http(url: &str) -> anyhow::Result<Self> {
         Self::new(
             url,
             vec![
@@ -85,8 +244,9 @@ impl Source {
     ///
     /// # Returns
     ///
-    /// A new instance of `Source` with SOCKS protocol types.
-    pub fn socks(url: &str) -> Self {
+    /// A result containing the `Source` with SOCKS protocol types, or an error if the URL is
+    /// invalid.
+    pub fn socks(url: &str) -> anyhow::Result<Self> {
         Self::new(url, vec![Protocol::Socks4, Protocol::Socks5])
     }
 }
@@ -8,14 +8,19 @@ pub struct ProxyscrapeProvider;
 
 #[async_trait]
 impl IProxyTrait for ProxyscrapeProvider {
+    /// Returns the name identifying the provider.
+    fn name(&self) -> &'static str {
+        "proxyscrape"
+    }
+
     /// Returns a list of sources from which proxies can be fetched.
     ///
     /// # Returns
     ///
-    /// A vector of `Source` objects representing the proxy sources.
-    fn sources(&self) -> Vec<Source> {
-        vec![
-            Source::all("https://api.proxyscrape.com/v4/free-proxy-list/get?request=display_proxies&proxy_format=ipport&format=text"),
-        ]
+    /// A result containing the `Source` objects representing the proxy sources.
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        Ok(vec![
+            Source::all("https://api.proxyscrape.com/v4/free-proxy-list/get?request=display_proxies&proxy_format=ipport&format=text")?,
+        ])
     }
 }
@@ -15,20 +15,30 @@ use tokio::{net::TcpStream, time};
 
 use async_trait::async_trait;
 
-use crate::{negotiators::NegotiatorTrait, proxy::models::Proxy};
+use crate::{
+    negotiators::NegotiatorTrait,
+    proxy::models::{Proxy, Timings},
+    Error,
+};
 
+/// A value obtained through a proxy, along with the time spent getting it.
 #[derive(Debug)]
 pub struct ProxyRuntimes<T> {
     pub inner: T,
-    pub runtimes: Vec<f64>,
+    pub timings: Timings,
 }
 
 impl<T> ProxyRuntimes<T> {
+    /// Records the measured stages in the timings of the proxy.
     pub fn apply(&self, proxy: &mut Proxy) {
-        proxy.runtimes.extend_from_slice(&self.runtimes);
+        proxy.timings.merge(&self.timings);
     }
 }
 
+/// The DER encoded certificate presented by the target of a request sent over TLS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCertificate(pub Vec<u8>);
+
 #[async_trait]
 pub trait ProxyClient {
     fn host(&self) -> Cow<'static, str>;
@@ -37,70 +47,140 @@ pub trait ProxyClient {
     ///
     /// # Returns
     ///
-    /// A tuple containing a `TcpStream` if the connection is successful,
-    /// and an array with the elapsed time in seconds as `f64`.
-    /// If the connection fails, it returns an error.
+    /// A `TcpStream` if the connection is successful, with the elapsed time recorded in
+    /// `tcp_connect`. If the connection fails, it returns an error.
     async fn connect_timeout(
         &mut self,
         timeout: Duration,
-    ) -> anyhow::Result<ProxyRuntimes<TcpStream>> {
-        let start_time = time::Instant::now();
+    ) -> crate::Result<ProxyRuntimes<TcpStream>> {
         self.log_trace("Starting TCP connection");
 
         let host = self.host();
+        let start_time = time::Instant::now();
+        let result = time::timeout(timeout, TcpStream::connect(host.into_owned())).await;
         let elapsed_time = start_time.elapsed();
-        let tcp_stream = time::timeout(timeout, TcpStream::connect(host.into_owned())).await??;
-        let runtimes = vec![elapsed_time.as_secs_f64()];
+        let tcp_stream = match result {
+            Ok(Ok(tcp_stream)) => tcp_stream,
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                return Err(Error::ConnectionRefused)
+            }
+            Ok(Err(e)) => return Err(Error::Io(e)),
+            Err(_) => return Err(Error::ConnectTimeout),
+        };
         self.log_trace(format!("Connected in {:?}", elapsed_time));
 
         Ok(ProxyRuntimes {
             inner: tcp_stream,
-            runtimes,
+            timings: Timings {
+                tcp_connect: Some(elapsed_time.as_secs_f64()),
+                ..Default::default()
+            },
         })
     }
 
+    /// Sends a request through the proxy, accepting any certificate when TLS is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: The request to send.
+    /// * `negotiator`: The negotiator of the proxy's protocol, if any.
+    /// * `timeout`: The duration allowed for each stage.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the response and the time spent in each stage. The certificate
+    /// presented over TLS is available as a `PeerCertificate` extension of the response.
     async fn send_request<B, N>(
         &mut self,
         req: Request<B>,
         negotiator: Option<N>,
         timeout: Duration,
-    ) -> anyhow::Result<ProxyRuntimes<Response<Incoming>>>
+    ) -> crate::Result<ProxyRuntimes<Response<Incoming>>>
+    where
+        B: Body + 'static + Debug + Send,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        N: NegotiatorTrait + Sync + Send,
+    {
+        self.send(req, negotiator, timeout, false).await
+    }
+
+    /// Sends a request through the proxy, verifying the certificate of the target when TLS
+    /// is used.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the response, or an `Error::Tls` if the certificate presented
+    /// through the proxy is not valid for the target.
+    async fn send_request_verified<B, N>(
+        &mut self,
+        req: Request<B>,
+        negotiator: Option<N>,
+        timeout: Duration,
+    ) -> crate::Result<ProxyRuntimes<Response<Incoming>>>
+    where
+        B: Body + 'static + Debug + Send,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        N: NegotiatorTrait + Sync + Send,
+    {
+        self.send(req, negotiator, timeout, true).await
+    }
+
+    async fn send<B, N>(
+        &mut self,
+        req: Request<B>,
+        negotiator: Option<N>,
+        timeout: Duration,
+        verify_tls: bool,
+    ) -> crate::Result<ProxyRuntimes<Response<Incoming>>>
     where
         B: Body + 'static + Debug + Send,
         B::Data: Send,
         B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
         N: NegotiatorTrait + Sync + Send,
     {
+        let start_time = time::Instant::now();
         let tcp = self.connect_timeout(timeout).await?;
         let mut stream = tcp.inner;
-        let mut runtimes = tcp.runtimes;
+        let mut timings = tcp.timings;
 
         let mut use_tls = false;
 
         if let Some(negotiator) = negotiator {
             let proxy_host = self.host();
-            if let Err(e) = negotiator
-                .negotiate(&mut stream, &mut runtimes, &proxy_host, req.uri())
-                .await
-            {
-                anyhow::bail!("Failed to negotiate: {}", e);
-            }
+            time::timeout(
+                timeout,
+                negotiator.negotiate(&mut stream, &mut timings, &proxy_host, req.uri()),
+            )
+            .await
+            .map_err(|_| Error::Timeout)??;
             use_tls = negotiator.with_tls();
         }
 
-        if use_tls || req.uri().scheme_str().unwrap_or("") == "https" {
-            time::timeout(timeout, self.send_with_tls(req, stream, runtimes)).await?
+        let mut response = if use_tls || req.uri().scheme_str().unwrap_or("") == "https" {
+            time::timeout(
+                timeout,
+                self.send_with_tls(req, stream, timings, verify_tls),
+            )
+            .await
+            .map_err(|_| Error::Timeout)??
         } else {
-            time::timeout(timeout, self.send_without_tls(req, stream, runtimes)).await?
-        }
+            time::timeout(timeout, self.send_without_tls(req, stream, timings))
+                .await
+                .map_err(|_| Error::Timeout)??
+        };
+        response.timings.total = Some(start_time.elapsed().as_secs_f64());
+        Ok(response)
     }
 
     async fn send_with_tls<B>(
         &mut self,
         req: Request<B>,
         stream: TcpStream,
-        mut runtimes: Vec<f64>,
-    ) -> anyhow::Result<ProxyRuntimes<Response<Incoming>>>
+        mut timings: Timings,
+        verify_tls: bool,
+    ) -> crate::Result<ProxyRuntimes<Response<Incoming>>>
     where
         B: Body + 'static + Debug + Send,
         B::Data: Send,
@@ -110,24 +190,33 @@ pub trait ProxyClient {
         let start_time = time::Instant::now();
 
         let tls_connector = TlsConnector::builder()
-            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_certs(!verify_tls)
             .build()?;
         let connector = tokio_native_tls::TlsConnector::from(tls_connector);
 
-        let host = self.host();
-        let tls_stream = connector
-            .connect(host.split(':').next().unwrap(), stream)
-            .await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
+        // The certificate is presented by the target the proxy tunnels to
+        let proxy_host = self.host();
+        let domain = req
+            .uri()
+            .host()
+            .unwrap_or_else(|| proxy_host.split(':').next().unwrap())
+            .to_string();
+        let tls_stream = connector.connect(&domain, stream).await?;
+        timings.tls_handshake = Some(start_time.elapsed().as_secs_f64());
         self.log_trace("TLS connection established successfully");
+        let certificate = tls_stream
+            .get_ref()
+            .peer_certificate()?
+            .map(|certificate| certificate.to_der())
+            .transpose()?
+            .map(PeerCertificate);
 
-        let start_time = time::Instant::now();
         let io = TokioIo::new(tls_stream);
         let (mut sender, conn) = handshake(io).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
 
         let host = self.host();
-        let handler = tokio::task::spawn(async move {
+        // The connection is driven until the response body has been read and dropped
+        tokio::task::spawn(async move {
             if let Err(err) = conn.await {
                 #[cfg(feature = "log")]
                 if log::max_level().eq(&log::LevelFilter::Trace) {
@@ -138,13 +227,15 @@ pub trait ProxyClient {
 
         self.log_trace(format!("Sending request: {:?}", req));
         let start_time = time::Instant::now();
-        let response = sender.send_request(req).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
-        handler.abort();
+        let mut response = sender.send_request(req).await?;
+        timings.ttfb = Some(start_time.elapsed().as_secs_f64());
+        if let Some(certificate) = certificate {
+            response.extensions_mut().insert(certificate);
+        }
 
         Ok(ProxyRuntimes {
             inner: response,
-            runtimes,
+            timings,
         })
     }
 
@@ -152,20 +243,19 @@ pub trait ProxyClient {
         &mut self,
         req: Request<B>,
         stream: TcpStream,
-        mut runtimes: Vec<f64>,
-    ) -> anyhow::Result<ProxyRuntimes<Response<Incoming>>>
+        mut timings: Timings,
+    ) -> crate::Result<ProxyRuntimes<Response<Incoming>>>
     where
         B: Body + 'static + Debug + Send,
         B::Data: Send,
         B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
     {
-        let start_time = time::Instant::now();
         let io = TokioIo::new(stream);
         let (mut sender, conn) = handshake(io).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
 
         let host = self.host();
-        let handler = tokio::task::spawn(async move {
+        // The connection is driven until the response body has been read and dropped
+        tokio::task::spawn(async move {
             if let Err(err) = conn.await {
                 #[cfg(feature = "log")]
                 if log::max_level().eq(&log::LevelFilter::Trace) {
@@ -177,12 +267,11 @@ pub trait ProxyClient {
         self.log_trace(format!("Sending request: {:?}", req));
         let start_time = time::Instant::now();
         let response = sender.send_request(req).await?;
-        runtimes.push(start_time.elapsed().as_secs_f64());
-        handler.abort();
+        timings.ttfb = Some(start_time.elapsed().as_secs_f64());
 
         Ok(ProxyRuntimes {
             inner: response,
-            runtimes,
+            timings,
         })
     }
 
@@ -1,14 +1,9 @@
 pub mod client;
 pub mod models;
+pub mod score;
 
 /*
 
-static HTTPS_JUDGES: [&str; 4] = [
-    "https://httpbin.org/get?show_env",
-    "https://www.proxyjudge.info",
-    "https://www.proxy-listen.de/azenv.php",
-    "https://httpheader.net/azenv.php",
-];
 static SMTP_JUDGES: [&str; 2] = ["smtp://smtp.gmail.com", "smtp://aspmx.l.google.com"];
 
 
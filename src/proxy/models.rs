@@ -1,11 +1,12 @@
 use std::{
     borrow::Cow,
+    cmp::Ordering,
     fmt::Display,
     net::Ipv4Addr,
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use serde::{Serialize, Serializer};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 
 use crate::geolookup::models::GeoData;
 
@@ -32,6 +33,16 @@ pub enum Protocol {
     Connect(u16),
 }
 
+impl Protocol {
+    /// Indicates if two protocols are the same, whatever the anonymity level or CONNECT port.
+    pub fn is_same_kind(&self, other: &Protocol) -> bool {
+        match (self, other) {
+            (Self::Http(_), Self::Http(_)) | (Self::Connect(_), Self::Connect(_)) => true,
+            _ => self == other,
+        }
+    }
+}
+
 impl Display for Protocol {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -83,15 +94,177 @@ impl ProxyType {
     }
 }
 
-fn serialize_runtimes<S>(runtimes: &[f64], serializer: S) -> Result<S::Ok, S::Error>
+/// Records a source a proxy was seen in.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProxyOrigin {
+    /// Name of the provider owning the source.
+    pub provider: Cow<'static, str>,
+    /// URL of the source.
+    pub url: String,
+    /// Time when the proxy was first parsed from this source.
+    pub first_seen: f64,
+}
+
+impl ProxyOrigin {
+    /// Creates a new `ProxyOrigin` for the given provider and source URL, seen now.
+    pub fn new(provider: impl Into<Cow<'static, str>>, url: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            url: url.into(),
+            first_seen: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs_f64(),
+        }
+    }
+}
+
+/// Time spent in each stage of a request through a proxy, in seconds.
+///
+/// Stages that were not reached, or do not apply to the protocol, are `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Timings {
+    /// Opening the TCP connection to the proxy.
+    pub tcp_connect: Option<f64>,
+    /// Negotiating the protocol with the proxy (CONNECT, SOCKS handshakes).
+    pub negotiate: Option<f64>,
+    /// Performing the TLS handshake with the target through the proxy.
+    pub tls_handshake: Option<f64>,
+    /// From sending the request until the response headers arrived.
+    pub ttfb: Option<f64>,
+    /// The whole exchange, from connecting until the response was read.
+    pub total: Option<f64>,
+}
+
+impl Timings {
+    /// Adds the duration of a negotiation step to `negotiate`.
+    pub fn add_negotiate(&mut self, seconds: f64) {
+        self.negotiate = Some(self.negotiate.unwrap_or_default() + seconds);
+    }
+
+    /// Overwrites the stages measured in `other`, keeping the others.
+    pub fn merge(&mut self, other: &Timings) {
+        self.tcp_connect = other.tcp_connect.or(self.tcp_connect);
+        self.negotiate = other.negotiate.or(self.negotiate);
+        self.tls_handshake = other.tls_handshake.or(self.tls_handshake);
+        self.ttfb = other.ttfb.or(self.ttfb);
+        self.total = other.total.or(self.total);
+    }
+}
+
+/// Changes a proxy made to a known payload fetched through it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Tampering {
+    /// The body differs from the original, e.g. because ads or scripts were injected.
+    pub body_modified: bool,
+    /// Names of the response headers the proxy added, in lowercase. Proxies may add their
+    /// own headers, so these alone do not count as tampering.
+    pub added_headers: Vec<String>,
+    /// Names of the content headers the proxy removed, in lowercase.
+    pub removed_headers: Vec<String>,
+    /// Names of the content headers the proxy changed the value of, in lowercase.
+    pub changed_headers: Vec<String>,
+}
+
+impl Tampering {
+    /// Indicates if the proxy changed the body, or removed or changed a content header.
+    pub fn is_tampered(&self) -> bool {
+        self.body_modified || !self.removed_headers.is_empty() || !self.changed_headers.is_empty()
+    }
+}
+
+/// Stage a protocol check had reached when it ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckPhase {
+    /// Opening the TCP connection to the proxy.
+    Connect,
+    /// Negotiating the protocol with the proxy.
+    Negotiate,
+    /// Sending the request through the proxy and reading the response.
+    Request,
+    /// Reading the judge's verdict from the response.
+    Judge,
+    /// Checking the proxy again to measure its reliability.
+    Reliability,
+    /// Matching the exit location against the exit geo filter.
+    Filter,
+    /// Comparing a known payload fetched through the proxy with the original.
+    Tampering,
+    /// Measuring the download throughput.
+    Bandwidth,
+    /// Every stage passed.
+    Done,
+}
+
+impl Display for CheckPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect => write!(f, "connect"),
+            Self::Negotiate => write!(f, "negotiate"),
+            Self::Request => write!(f, "request"),
+            Self::Judge => write!(f, "judge"),
+            Self::Reliability => write!(f, "reliability"),
+            Self::Filter => write!(f, "filter"),
+            Self::Tampering => write!(f, "tampering"),
+            Self::Bandwidth => write!(f, "bandwidth"),
+            Self::Done => write!(f, "done"),
+        }
+    }
+}
+
+/// Records how checking a proxy for one protocol went.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckOutcome {
+    /// The protocol that was checked.
+    pub protocol: Protocol,
+    /// The stage the check reached.
+    pub phase: CheckPhase,
+    /// Short identifier of the failure, e.g. `connect_timeout`; `None` if the check passed.
+    pub error_kind: Option<&'static str>,
+    /// Description of the failure; `None` if the check passed.
+    pub error: Option<String>,
+    /// URL of the last judge requested, if any.
+    pub judge: Option<String>,
+    /// Time spent in the stages that completed.
+    pub timings: Timings,
+    /// Time when the check started.
+    pub checked_on: f64,
+}
+
+impl CheckOutcome {
+    /// Creates a new `CheckOutcome` for the given protocol, starting now.
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            phase: CheckPhase::Connect,
+            error_kind: None,
+            error: None,
+            judge: None,
+            timings: Timings::default(),
+            checked_on: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs_f64(),
+        }
+    }
+
+    /// Indicates if the check passed.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Serializes the timings along with the total time as `average_response_time`, which
+/// existing consumers of the JSON output read; 0 if the proxy was not validated.
+fn serialize_timings<S>(timings: &Timings, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    if runtimes.is_empty() {
-        return serializer.serialize_f64(0.0);
-    }
-    let sum: f64 = runtimes.iter().sum();
-    serializer.serialize_f64(sum / runtimes.len() as f64)
+    let mut state = serializer.serialize_struct("Timings", 2)?;
+    state.serialize_field("average_response_time", &timings.total.unwrap_or_default())?;
+    state.serialize_field("timings", timings)?;
+    state.end()
 }
 
 /// Represents a proxy with its details.
@@ -101,32 +274,82 @@ pub struct Proxy {
     pub ip: Ipv4Addr,
     /// Port number of the proxy.
     pub port: u16,
-    /// Geographical data associated with the proxy.
+    /// Geographical data associated with the proxy's listening address.
     pub geo: GeoData,
-    /// Response times for the proxy.
-    #[serde(
-        rename = "average_response_time",
-        serialize_with = "serialize_runtimes"
-    )]
-    pub runtimes: Vec<f64>,
+    /// Address the proxy's traffic appears from, as seen by the judge during validation;
+    /// `None` if the judge's answer does not report it.
+    pub exit_ip: Option<Ipv4Addr>,
+    /// Geographical data associated with the exit address, if it was looked up.
+    pub exit_geo: Option<GeoData>,
+    /// Time spent in each stage of the request that validated the proxy.
+    #[serde(flatten, serialize_with = "serialize_timings")]
+    pub timings: Timings,
+    /// Download throughput through the proxy in bytes per second, if it was measured.
+    pub bandwidth: Option<f64>,
+    /// Share of the reliability checks the proxy passed, if the reliability mode ran.
+    pub success_rate: Option<f64>,
+    /// Standard deviation of the response time across reliability checks, in seconds.
+    pub jitter: Option<f64>,
+    /// Whether the proxy replaced the certificate of the HTTPS judge, if it was checked and
+    /// the genuine certificate is known.
+    pub tls_intercepted: Option<bool>,
+    /// Changes the proxy made to a known payload, if it was checked.
+    pub tampering: Option<Tampering>,
+    /// Composite score between 0 and 1, if the proxy was ranked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
     #[serde(skip)]
     pub expected_types: Vec<Protocol>,
     #[serde(rename = "type")]
     pub proxy_type: Option<ProxyType>,
+    /// Sources the proxy was seen in, in the order they were seen.
+    pub sources: Vec<ProxyOrigin>,
+    /// Outcome of each check, filled in when the validator reports failures.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<CheckOutcome>,
 }
 
 impl Proxy {
-    /// Calculates the average proxy response time.
+    /// Indicates if the proxy is a failure report: its last check failed and it has no type.
+    pub fn is_failed(&self) -> bool {
+        self.proxy_type.is_none() && self.checks.last().is_some_and(|outcome| !outcome.is_ok())
+    }
+
+    /// Returns the total time of the request that validated the proxy.
     ///
     /// # Returns
     ///
-    /// The average response time as a `f64`. Returns 0.0 if no runtimes are recorded.
-    pub fn avg_response_time(&self) -> f64 {
-        if self.runtimes.is_empty() {
-            return 0.0;
+    /// The response time in seconds, or `None` if the proxy was not validated.
+    pub fn response_time(&self) -> Option<f64> {
+        self.timings.total
+    }
+
+    /// Compares proxies by response time, fastest first; proxies without one come last.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The proxy to compare with.
+    ///
+    /// # Returns
+    ///
+    /// The ordering of the two proxies, suited to `sort_by`.
+    pub fn cmp_response_time(&self, other: &Proxy) -> Ordering {
+        match (self.response_time(), other.response_time()) {
+            (Some(left), Some(right)) => left.total_cmp(&right),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
         }
-        let sum: f64 = self.runtimes.iter().sum();
-        sum / self.runtimes.len() as f64
+    }
+
+    /// Returns the total time of the request that validated the proxy.
+    ///
+    /// # Returns
+    ///
+    /// The response time in seconds. Returns 0.0 if the proxy was not validated.
+    #[deprecated(note = "use `response_time` or `timings` instead")]
+    pub fn avg_response_time(&self) -> f64 {
+        self.response_time().unwrap_or_default()
     }
 
     /// Returns the proxy in `<ip>:<port>` format.
@@ -138,6 +361,28 @@ impl Proxy {
         Cow::Owned(format!("{}:{}", self.ip, self.port))
     }
 
+    /// Returns the geographical data of the address the proxy's traffic appears from.
+    ///
+    /// # Returns
+    ///
+    /// The exit geo data if it was looked up, otherwise the entry geo data.
+    pub fn effective_geo(&self) -> &GeoData {
+        self.exit_geo.as_ref().unwrap_or(&self.geo)
+    }
+
+    /// Indicates if the proxy was seen in the source with the given URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The URL of the source.
+    ///
+    /// # Returns
+    ///
+    /// `true` if one of the proxy's origins is that source.
+    pub fn seen_in(&self, url: &str) -> bool {
+        self.sources.iter().any(|origin| origin.url == url)
+    }
+
     /// Converts the proxy details to JSON format.
     ///
     /// # Returns
@@ -154,9 +399,19 @@ impl Default for Proxy {
             ip: Ipv4Addr::new(0, 0, 0, 0),
             port: 0,
             geo: GeoData::default(),
-            runtimes: vec![],
+            exit_ip: None,
+            exit_geo: None,
+            timings: Timings::default(),
+            bandwidth: None,
+            success_rate: None,
+            jitter: None,
+            tls_intercepted: None,
+            tampering: None,
+            score: None,
             expected_types: vec![],
             proxy_type: None,
+            sources: vec![],
+            checks: vec![],
         }
     }
 }
@@ -169,14 +424,29 @@ impl Display for Proxy {
             write!(f, "<Proxy --")?;
         }
 
+        let protocol = match (&self.proxy_type, self.checks.last()) {
+            (Some(proxy_type), _) if self.tls_intercepted == Some(true) => {
+                format!("{}: Intercepted", proxy_type.protocol)
+            }
+            (Some(proxy_type), _)
+                if self.tampering.as_ref().is_some_and(Tampering::is_tampered) =>
+            {
+                format!("{}: Tampered", proxy_type.protocol)
+            }
+            (Some(proxy_type), _) => proxy_type.protocol.to_string(),
+            (None, Some(outcome)) if !outcome.is_ok() => format!(
+                "{}: failed at {}: {}",
+                outcome.protocol,
+                outcome.phase,
+                outcome.error_kind.unwrap_or("error")
+            ),
+            (None, _) => "--".into(),
+        };
         write!(
             f,
             " {:.2}s [{}] {}:{}>",
-            self.avg_response_time(),
-            self.proxy_type
-                .as_ref()
-                .map(|v| format!("{}", v.protocol))
-                .unwrap_or("--".into()),
+            self.response_time().unwrap_or_default(),
+            protocol,
             self.ip,
             self.port
         )
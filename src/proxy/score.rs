@@ -0,0 +1,191 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use hashbrown::{HashMap, HashSet};
+
+use super::models::{Anonymity, Protocol, Proxy};
+
+/// Relative weights of the components of a proxy's score.
+///
+/// Each component is normalized to `0.0..=1.0`. Components that were not measured, such as
+/// the bandwidth when the test is disabled, are left out and the other weights count for more.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreWeights {
+    /// Total response time; one second scores 0.5.
+    pub latency: f64,
+    /// Anonymity level; elite scores 1, transparent 0.
+    pub anonymity: f64,
+    /// Share of the reliability checks passed.
+    pub success_rate: f64,
+    /// Download throughput; 1 MB/s scores 0.5.
+    pub bandwidth: f64,
+    /// Number of protocols the proxy supports; four or more score 1.
+    pub protocols: f64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            latency: 0.35,
+            anonymity: 0.2,
+            success_rate: 0.2,
+            bandwidth: 0.15,
+            protocols: 0.1,
+        }
+    }
+}
+
+impl FromStr for ScoreWeights {
+    type Err = String;
+
+    /// Parses `<component>=<weight>` pairs separated by commas, e.g. `latency=0.5,bandwidth=0`.
+    ///
+    /// Components that are not given keep their default weight.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut weights = Self::default();
+        for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (name, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected <component>=<weight>, got {:?}", pair))?;
+            let weight = weight
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| *weight >= 0.0)
+                .ok_or_else(|| format!("invalid weight for {}: {:?}", name, weight))?;
+            match name.trim() {
+                "latency" => weights.latency = weight,
+                "anonymity" => weights.anonymity = weight,
+                "success_rate" | "success-rate" => weights.success_rate = weight,
+                "bandwidth" => weights.bandwidth = weight,
+                "protocols" => weights.protocols = weight,
+                name => return Err(format!("unknown score component: {:?}", name)),
+            }
+        }
+        Ok(weights)
+    }
+}
+
+impl ScoreWeights {
+    /// Computes the score of a proxy.
+    ///
+    /// # Arguments
+    ///
+    /// * `proxy`: The proxy to score.
+    /// * `protocol_count`: The number of protocols the proxy was validated for.
+    ///
+    /// # Returns
+    ///
+    /// The score, between 0 (worst) and 1 (best).
+    pub fn score(&self, proxy: &Proxy, protocol_count: usize) -> f64 {
+        let anonymity = proxy
+            .proxy_type
+            .as_ref()
+            .map(|proxy_type| match &proxy_type.protocol {
+                Protocol::Http(Anonymity::Elite) => 1.0,
+                Protocol::Http(Anonymity::Anonymous) => 0.5,
+                Protocol::Http(Anonymity::Unknown) => 0.25,
+                Protocol::Http(Anonymity::Transparent) => 0.0,
+                // Tunnels forward the traffic untouched
+                _ => 1.0,
+            });
+        let components = [
+            (
+                self.latency,
+                proxy.response_time().map(|time| 1.0 / (1.0 + time)),
+            ),
+            (self.anonymity, anonymity),
+            (self.success_rate, proxy.success_rate),
+            (
+                self.bandwidth,
+                proxy
+                    .bandwidth
+                    .map(|bandwidth| bandwidth / (bandwidth + 1_000_000.0)),
+            ),
+            (
+                self.protocols,
+                (protocol_count > 0).then(|| protocol_count.min(4) as f64 / 4.0),
+            ),
+        ];
+
+        let (total, weights) = components
+            .iter()
+            .filter_map(|(weight, value)| value.map(|value| (weight * value, weight)))
+            .fold((0.0, 0.0), |(total, weights), (value, weight)| {
+                (total + value, weights + weight)
+            });
+        if weights > 0.0 {
+            total / weights
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Scores a batch of proxies, counting the protocols validated for each address in the batch.
+///
+/// # Arguments
+///
+/// * `proxies`: The proxies to score; their `score` is filled in.
+/// * `weights`: The weights of the score components.
+pub fn rank(proxies: &mut [Proxy], weights: &ScoreWeights) {
+    let mut protocols: HashMap<String, HashSet<String>> = HashMap::new();
+    for proxy in proxies.iter() {
+        if let Some(proxy_type) = &proxy.proxy_type {
+            // Anonymity levels of HTTP are the same protocol
+            let protocol = match proxy_type.protocol {
+                Protocol::Http(_) => "HTTP".to_string(),
+                ref protocol => protocol.to_string(),
+            };
+            protocols
+                .entry(proxy.as_text().into_owned())
+                .or_default()
+                .insert(protocol);
+        }
+    }
+    for proxy in proxies.iter_mut() {
+        let protocol_count = protocols
+            .get(proxy.as_text().as_ref())
+            .map_or(0, HashSet::len);
+        proxy.score = Some(weights.score(proxy, protocol_count));
+    }
+}
+
+/// Orders in which proxies can be sorted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Highest score first; requires `rank`.
+    Score,
+    /// Fastest response time first.
+    Latency,
+    /// By country code of the exit location, then fastest first.
+    Country,
+}
+
+/// Sorts proxies; proxies missing the sorted value come last.
+///
+/// # Arguments
+///
+/// * `proxies`: The proxies to sort.
+/// * `key`: The order to sort them in.
+pub fn sort(proxies: &mut [Proxy], key: SortKey) {
+    match key {
+        SortKey::Score => proxies.sort_by(|left, right| match (left.score, right.score) {
+            (Some(left), Some(right)) => right.total_cmp(&left),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }),
+        SortKey::Latency => proxies.sort_by(Proxy::cmp_response_time),
+        SortKey::Country => proxies.sort_by(|left, right| {
+            let left_country = &left.effective_geo().iso_code;
+            let right_country = &right.effective_geo().iso_code;
+            match (left_country, right_country) {
+                (Some(left_country), Some(right_country)) => left_country.cmp(right_country),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then_with(|| left.cmp_response_time(right))
+        }),
+    }
+}
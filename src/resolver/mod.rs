@@ -0,0 +1,346 @@
+mod stun;
+
+use std::{
+    env,
+    fmt::{Display, Formatter},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use fake::{faker::internet::en::UserAgent, Fake};
+use http_body_util::{BodyExt, Empty};
+use hyper::{body::Bytes, Request};
+use hyper_tls::HttpsConnector;
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use tokio::time;
+use trust_dns_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+
+/// Environment variable read by `Strategy::Env` in the default detector.
+pub const PUBLIC_IP_ENV: &str = "FLUXY_PUBLIC_IP";
+
+/// The public addresses of this machine.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublicIp {
+    /// Public IPv4 address, if one was detected.
+    pub v4: Option<Ipv4Addr>,
+    /// Public IPv6 address, if one was detected.
+    pub v6: Option<Ipv6Addr>,
+}
+
+impl PublicIp {
+    /// Creates a `PublicIp` from a list of addresses, keeping the first of each family.
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses`: The detected addresses.
+    ///
+    /// # Returns
+    ///
+    /// A new `PublicIp`, empty if the list is.
+    pub fn from_addresses(addresses: &[IpAddr]) -> Self {
+        Self {
+            v4: addresses.iter().find_map(|address| match address {
+                IpAddr::V4(v4) => Some(*v4),
+                IpAddr::V6(_) => None,
+            }),
+            v6: addresses.iter().find_map(|address| match address {
+                IpAddr::V6(v6) => Some(*v6),
+                IpAddr::V4(_) => None,
+            }),
+        }
+    }
+
+    /// Indicates if no address was detected.
+    pub fn is_empty(&self) -> bool {
+        self.v4.is_none() && self.v6.is_none()
+    }
+
+    /// Indicates if an address of both families was detected.
+    pub fn is_complete(&self) -> bool {
+        self.v4.is_some() && self.v6.is_some()
+    }
+
+    /// Fills the missing families with the addresses of another detection.
+    fn fill(&mut self, other: PublicIp) {
+        self.v4 = self.v4.or(other.v4);
+        self.v6 = self.v6.or(other.v6);
+    }
+
+    /// Indicates if one of the addresses appears in the given text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text`: The text to search, e.g. the body returned by a proxy judge.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the IPv4 or the IPv6 address is found in the text.
+    pub fn is_in(&self, text: &str) -> bool {
+        self.v4.is_some_and(|v4| text.contains(&v4.to_string()))
+            || self.v6.is_some_and(|v6| text.contains(&v6.to_string()))
+    }
+}
+
+impl Display for PublicIp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.v4, &self.v6) {
+            (Some(v4), Some(v6)) => write!(f, "{}, {}", v4, v6),
+            (Some(v4), None) => write!(f, "{}", v4),
+            (None, Some(v6)) => write!(f, "{}", v6),
+            (None, None) => write!(f, "--"),
+        }
+    }
+}
+
+/// A way of detecting the public address of this machine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Strategy {
+    /// Uses the given addresses as-is; a local stand-in for offline use and tests.
+    Fixed(PublicIp),
+    /// Reads comma separated addresses from an environment variable.
+    Env(String),
+    /// Resolves a name on a DNS server answering with the address of the client.
+    Dns {
+        server: SocketAddr, // Address of the DNS server.
+        name: String,       // Name resolving to the client address.
+    },
+    /// Requests an HTTP endpoint echoing the address of the client as plain text.
+    Http(String),
+    /// Sends a STUN binding request to a `<host>:<port>` server.
+    Stun(String),
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fixed(_) => write!(f, "fixed"),
+            Self::Env(var) => write!(f, "env:{}", var),
+            Self::Dns { server, name } => write!(f, "dns:{}@{}", name, server),
+            Self::Http(url) => write!(f, "{}", url),
+            Self::Stun(server) => write!(f, "stun:{}", server),
+        }
+    }
+}
+
+impl Strategy {
+    /// Detects the public addresses with this strategy.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout`: The duration to wait for a network answer.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the detected addresses, or an error if none could be found.
+    async fn detect(&self, timeout: Duration) -> anyhow::Result<PublicIp> {
+        let public_ip = match self {
+            Self::Fixed(public_ip) => public_ip.clone(),
+            Self::Env(var) => match env::var(var) {
+                Ok(value) => parse_addresses(&value)?,
+                Err(_) => anyhow::bail!("{} is not set", var),
+            },
+            Self::Dns { server, name } => {
+                let resolver = TokioAsyncResolver::tokio(
+                    ResolverConfig::from_parts(
+                        None,
+                        vec![],
+                        NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), false),
+                    ),
+                    ResolverOpts::default(),
+                );
+                let lookup = time::timeout(timeout, resolver.lookup_ip(name.as_str())).await??;
+                PublicIp::from_addresses(&lookup.iter().collect::<Vec<_>>())
+            }
+            Self::Http(url) => {
+                let client = Client::builder(TokioExecutor::new())
+                    .build::<_, Empty<Bytes>>(HttpsConnector::new());
+                let req = Request::get(url.as_str())
+                    .header(hyper::header::USER_AGENT, UserAgent().fake::<&str>())
+                    .body(Empty::<Bytes>::new())?;
+                let body = time::timeout(timeout, async {
+                    let response = client.request(req).await?;
+                    if !response.status().is_success() {
+                        anyhow::bail!("Unexpected status {}", response.status());
+                    }
+                    Ok(response.collect().await?.to_bytes())
+                })
+                .await??;
+                parse_addresses(&String::from_utf8_lossy(&body))?
+            }
+            Self::Stun(server) => {
+                PublicIp::from_addresses(&[stun::mapped_address(server, timeout).await?])
+            }
+        };
+
+        if public_ip.is_empty() {
+            anyhow::bail!("No address found");
+        }
+        Ok(public_ip)
+    }
+}
+
+/// Parses comma or whitespace separated addresses.
+fn parse_addresses(value: &str) -> anyhow::Result<PublicIp> {
+    let addresses = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<IpAddr>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Invalid address in {:?}: {}", value.trim(), e))?;
+    Ok(PublicIp::from_addresses(&addresses))
+}
+
+/// Error returned when the public address of this machine cannot be detected.
+#[derive(Debug)]
+pub struct DetectError {
+    /// The error of each strategy that was tried, in order.
+    pub failures: Vec<(String, String)>,
+}
+
+impl Display for DetectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.failures.is_empty() {
+            return write!(f, "Failed to detect public IP: no strategy configured");
+        }
+        write!(
+            f,
+            "Failed to detect public IP, please check internet connection"
+        )?;
+        for (strategy, error) in &self.failures {
+            write!(f, "\n  {}: {}", strategy, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DetectError {}
+
+/// Detects the public addresses of this machine, trying each strategy until the IPv4
+/// address is known, and the IPv6 one too if requested.
+#[derive(Debug, Clone)]
+pub struct IpDetector {
+    strategies: Vec<Strategy>, // Strategies to try, in order.
+    timeout: Duration,         // Time allowed for each network strategy.
+    ipv6: bool,                // Keep trying strategies until the IPv6 address is known.
+}
+
+impl Default for IpDetector {
+    /// Tries `FLUXY_PUBLIC_IP`, then OpenDNS over IPv4 and IPv6, HTTP echo services and a
+    /// STUN server.
+    fn default() -> Self {
+        Self::new(vec![
+            Strategy::Env(PUBLIC_IP_ENV.to_string()),
+            Strategy::Dns {
+                server: SocketAddr::from(([208, 67, 222, 222], 53)),
+                name: "myip.opendns.com".to_string(),
+            },
+            // Answers with the IPv6 address, as the query is sent over IPv6
+            Strategy::Dns {
+                server: SocketAddr::from(([0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35], 53)),
+                name: "myip.opendns.com".to_string(),
+            },
+            Strategy::Http("https://api.ipify.org".to_string()),
+            Strategy::Http("https://api6.ipify.org".to_string()),
+            Strategy::Http("https://icanhazip.com".to_string()),
+            Strategy::Stun("stun.l.google.com:19302".to_string()),
+        ])
+    }
+}
+
+impl IpDetector {
+    /// Creates a new `IpDetector` with the given strategies.
+    ///
+    /// # Arguments
+    ///
+    /// * `strategies`: The strategies to try, in order.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `IpDetector`.
+    pub fn new(strategies: Vec<Strategy>) -> Self {
+        Self {
+            strategies,
+            timeout: Duration::from_secs(5),
+            ipv6: false,
+        }
+    }
+
+    /// Creates an `IpDetector` always answering with the given addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses`: The addresses standing in for the public ones.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `IpDetector` which never touches the network.
+    pub fn fixed(addresses: &[IpAddr]) -> Self {
+        Self::new(vec![Strategy::Fixed(PublicIp::from_addresses(addresses))])
+    }
+
+    /// Sets the time allowed for each network strategy.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets whether detection goes on until the IPv6 address is known.
+    ///
+    /// Proxies are reached over IPv4, so only that address is needed by default; waiting for
+    /// the IPv6 one would run every IPv6 strategy to its timeout on hosts without IPv6.
+    pub fn with_ipv6(mut self, ipv6: bool) -> Self {
+        self.ipv6 = ipv6;
+        self
+    }
+
+    /// Indicates if the addresses detected so far are all that is needed.
+    fn is_done(&self, public_ip: &PublicIp) -> bool {
+        public_ip.v4.is_some() && (!self.ipv6 || public_ip.v6.is_some())
+    }
+
+    /// Detects the public addresses of this machine.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the first address of each family found by the strategies before
+    /// the needed ones were known, or an error listing why each strategy failed if none found
+    /// an address.
+    pub async fn detect(&self) -> Result<PublicIp, DetectError> {
+        let start_time = Instant::now();
+        let mut public_ip = PublicIp::default();
+        let mut failures = vec![];
+
+        for strategy in &self.strategies {
+            if self.is_done(&public_ip) {
+                break;
+            }
+            match strategy.detect(self.timeout).await {
+                Ok(detected) => {
+                    #[cfg(feature = "log")]
+                    log::debug!(
+                        "Detected {} with {} in {:?}",
+                        detected,
+                        strategy,
+                        start_time.elapsed()
+                    );
+                    public_ip.fill(detected);
+                }
+                Err(e) => {
+                    #[cfg(feature = "log")]
+                    log::debug!("Failed to detect public IP with {}: {}", strategy, e);
+                    failures.push((strategy.to_string(), e.to_string()));
+                }
+            }
+        }
+
+        if public_ip.is_empty() {
+            return Err(DetectError { failures });
+        }
+        #[cfg(feature = "log")]
+        log::debug!("My IP: {} (in {:?})", public_ip, start_time.elapsed());
+        Ok(public_ip)
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use fake::rand::random;
+use tokio::{net::UdpSocket, time};
+
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+const MAPPED_ADDRESS: u16 = 0x0001;
+const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+/// Asks a STUN server for the address the request was received from (RFC 5389).
+///
+/// # Arguments
+///
+/// * `server`: The `<host>:<port>` of the STUN server.
+/// * `timeout`: The duration to wait for the response.
+///
+/// # Returns
+///
+/// A result containing the mapped address, or an error if the server did not answer
+/// with a valid binding response.
+pub(super) async fn mapped_address(server: &str, timeout: Duration) -> anyhow::Result<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(server).await?;
+
+    let transaction_id: [u8; 12] = random();
+    let mut request = Vec::with_capacity(20);
+    request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(&transaction_id);
+    socket.send(&request).await?;
+
+    let mut response = [0u8; 512];
+    let len = time::timeout(timeout, socket.recv(&mut response)).await??;
+    parse_response(&response[..len], &transaction_id)
+}
+
+/// Reads the mapped address out of a binding response.
+fn parse_response(response: &[u8], transaction_id: &[u8; 12]) -> anyhow::Result<IpAddr> {
+    if response.len() < 20
+        || u16::from_be_bytes([response[0], response[1]]) != BINDING_SUCCESS
+        || response[8..20] != transaction_id[..]
+    {
+        anyhow::bail!("Invalid STUN binding response");
+    }
+
+    let mut attributes = &response[20..];
+    let mut mapped = None;
+    while attributes.len() >= 4 {
+        let kind = u16::from_be_bytes([attributes[0], attributes[1]]);
+        let len = u16::from_be_bytes([attributes[2], attributes[3]]) as usize;
+        let Some(value) = attributes.get(4..4 + len) else {
+            break;
+        };
+        match kind {
+            XOR_MAPPED_ADDRESS => return parse_address(value, Some(transaction_id)),
+            MAPPED_ADDRESS => mapped = Some(parse_address(value, None)),
+            _ => {}
+        }
+        // Attributes are padded to a multiple of 4 bytes
+        let padded = (4 + len).next_multiple_of(4);
+        attributes = attributes.get(padded..).unwrap_or_default();
+    }
+    mapped.unwrap_or_else(|| anyhow::bail!("STUN response has no mapped address"))
+}
+
+/// Decodes a (XOR-)MAPPED-ADDRESS attribute value.
+fn parse_address(value: &[u8], xor_with: Option<&[u8; 12]>) -> anyhow::Result<IpAddr> {
+    let mut mask = MAGIC_COOKIE.to_be_bytes().to_vec();
+    match xor_with {
+        Some(transaction_id) => mask.extend_from_slice(transaction_id),
+        None => mask = vec![0; 16],
+    }
+    let unmask = |bytes: &[u8]| -> Vec<u8> {
+        bytes
+            .iter()
+            .zip(mask.iter())
+            .map(|(byte, mask)| byte ^ mask)
+            .collect()
+    };
+
+    match (value.get(1), value.get(4..)) {
+        (Some(0x01), Some(address)) if address.len() >= 4 => {
+            let octets: [u8; 4] = unmask(&address[..4]).try_into().unwrap_or_default();
+            Ok(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        (Some(0x02), Some(address)) if address.len() >= 16 => {
+            let octets: [u8; 16] = unmask(&address[..16]).try_into().unwrap_or_default();
+            Ok(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => anyhow::bail!("Invalid STUN mapped address"),
+    }
+}
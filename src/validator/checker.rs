@@ -1,23 +1,33 @@
 #![allow(unused, dead_code)]
 
-use std::time::Duration;
+use std::{net::Ipv4Addr, path::Path, time::Duration};
 
 use async_trait::async_trait;
 use fake::{faker::internet::en::UserAgent, Fake};
+use hashbrown::HashMap;
 use http_body_util::{BodyExt, Empty};
 use hyper::{
     body::{Bytes, Incoming},
     header::USER_AGENT,
-    Request, Response,
+    HeaderMap, Request, Response, StatusCode, Uri,
+};
+use hyper_tls::HttpsConnector;
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use native_tls::{Certificate, TlsConnector};
+use sha2::{Digest, Sha256};
+use tokio::{
+    net::TcpStream,
+    time::{self, Instant},
 };
 
 use crate::{
     negotiators::{HttpNegotiator, HttpsNegotiator},
     proxy::{
-        client::{ProxyClient, ProxyRuntimes},
-        models::{Anonymity, Protocol, Proxy},
+        client::{PeerCertificate, ProxyClient, ProxyRuntimes},
+        models::{Anonymity, CheckOutcome, Protocol, Proxy, Tampering, Timings},
     },
-    resolver::my_ip,
+    resolver::PublicIp,
+    Error,
 };
 
 static ANON_INTEREST: [&str; 15] = [
@@ -51,61 +61,694 @@ static HTTP_JUDGES: [&str; 10] = [
     "http://www2t.biglobe.ne.jp/~take52/test/env.cgi",
 ];
 
-async fn to_raw_response(response: Response<Incoming>) -> anyhow::Result<String> {
+static HTTPS_JUDGES: [&str; 4] = [
+    "https://httpbin.org/get?show_env",
+    "https://www.proxyjudge.info",
+    "https://www.proxy-listen.de/azenv.php",
+    "https://httpheader.net/azenv.php",
+];
+
+/// Response headers left out of the tampering check: hop-by-hop headers, headers that
+/// forwarding proxies and caches add or rewrite, and headers varying between two fetches.
+static IGNORED_HEADERS: [&str; 25] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "via",
+    "forwarded",
+    "x-forwarded-for",
+    "x-forwarded-host",
+    "x-forwarded-proto",
+    "x-cache",
+    "x-cache-hits",
+    "x-cache-lookup",
+    "x-cache-status",
+    "cache-status",
+    "x-served-by",
+    "warning",
+    "age",
+    "date",
+    "expires",
+    "last-modified",
+    "content-length",
+];
+
+/// Headers describing how the payload is decoded and rendered; a proxy removing or changing
+/// them tampers with the content.
+static CONTENT_HEADERS: [&str; 6] = [
+    "content-type",
+    "content-encoding",
+    "content-language",
+    "content-disposition",
+    "content-security-policy",
+    "x-content-type-options",
+];
+
+/// The judges of HTTPS checks and how their certificates are checked.
+#[derive(Debug, Clone, Default)]
+pub struct HttpsJudge {
+    url: Option<String>, // Self-hosted judge used instead of the public ones.
+    certificate: Option<Vec<u8>>, // DER certificate the judge must present, if pinned.
+    direct: HashMap<String, Vec<u8>>, // DER certificates the judges present without a proxy.
+}
+
+/// Reads a PEM or DER certificate to pin.
+///
+/// # Returns
+///
+/// A result containing the DER encoded certificate, or an error if it cannot be read.
+pub fn read_certificate(path: &Path) -> crate::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    let certificate = if bytes.starts_with(b"-----BEGIN") {
+        Certificate::from_pem(&bytes)?
+    } else {
+        Certificate::from_der(&bytes)?
+    };
+    Ok(certificate.to_der()?)
+}
+
+/// Connects to an HTTPS server directly and returns the certificate it presents.
+///
+/// Any certificate is accepted, so that an expired or self-signed one can still be compared
+/// with the one presented through a proxy.
+///
+/// # Arguments
+///
+/// * `url`: The URL of the server.
+/// * `timeout`: The duration allowed for the connection and the handshake.
+///
+/// # Returns
+///
+/// A result containing the DER encoded certificate, or an error if none was presented.
+pub async fn direct_certificate(url: &str, timeout: Duration) -> crate::Result<Vec<u8>> {
+    let uri = url.parse::<Uri>().map_err(anyhow::Error::from)?;
+    let host = uri
+        .host()
+        .ok_or_else(|| Error::Config(format!("{}: URL has no host", url)))?;
+    let port = uri.port_u16().unwrap_or(443);
+    let connector = tokio_native_tls::TlsConnector::from(
+        TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .build()?,
+    );
+    time::timeout(timeout, async {
+        let stream = TcpStream::connect((host, port)).await?;
+        let tls_stream = connector.connect(host, stream).await?;
+        match tls_stream.get_ref().peer_certificate()? {
+            Some(certificate) => Ok(certificate.to_der()?),
+            None => Err(Error::Judge {
+                url: url.to_string(),
+                status: None,
+                reason: "No certificate was presented".into(),
+            }),
+        }
+    })
+    .await
+    .map_err(|_| Error::Timeout)?
+}
+
+impl HttpsJudge {
+    /// Creates the judges of HTTPS checks.
+    ///
+    /// Unless a certificate is pinned, the certificate each judge presents on a direct
+    /// connection is recorded, to be compared with the one presented through proxies.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The self-hosted judge used instead of the public ones, if any.
+    /// * `certificate`: The DER certificate the judge must present, if pinned.
+    /// * `timeout`: The duration allowed for each direct connection.
+    ///
+    /// # Returns
+    ///
+    /// A new `HttpsJudge`; judges that cannot be reached directly have no recorded
+    /// certificate.
+    pub async fn new(url: Option<String>, certificate: Option<Vec<u8>>, timeout: Duration) -> Self {
+        let mut judge = Self {
+            url,
+            certificate,
+            direct: HashMap::new(),
+        };
+        if judge.certificate.is_some() {
+            return judge;
+        }
+
+        let handles = judge
+            .urls()
+            .into_iter()
+            .map(|url| {
+                let url = url.to_string();
+                tokio::spawn(async move {
+                    let certificate = direct_certificate(&url, timeout).await;
+                    (url, certificate)
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            match handle.await {
+                Ok((url, Ok(certificate))) => {
+                    judge.direct.insert(url, certificate);
+                }
+                Ok((_url, Err(_e))) => {
+                    #[cfg(feature = "log")]
+                    log::debug!("{}: Failed to read the judge certificate: {}", _url, _e);
+                }
+                Err(_) => {}
+            }
+        }
+        judge
+    }
+
+    /// Returns the URLs of the judges, in the order they are tried.
+    fn urls(&self) -> Vec<&str> {
+        match &self.url {
+            Some(url) => vec![url.as_str()],
+            None => HTTPS_JUDGES.to_vec(),
+        }
+    }
+
+    /// Sends a request to the judge through the proxy and checks the certificate it presents.
+    ///
+    /// A pinned certificate must match exactly. Otherwise the certificate chain is verified,
+    /// and if it is rejected the request is sent again accepting any certificate. The
+    /// certificate is then compared with the one the judge presents on a direct connection,
+    /// so that an interception is told apart from an untrusted judge or a failed handshake.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the response and whether the certificate was replaced, or `None`
+    /// if that cannot be told.
+    async fn request(
+        &self,
+        proxy: &mut Proxy,
+        url: &str,
+        timeout: Duration,
+    ) -> crate::Result<(ProxyRuntimes<Response<Incoming>>, Option<bool>)> {
+        let req = || {
+            Request::get(url)
+                .header(USER_AGENT, UserAgent().fake::<&str>())
+                .body(Empty::<Bytes>::new())
+                .map_err(anyhow::Error::from)
+        };
+
+        if let Some(pinned) = &self.certificate {
+            let response = proxy
+                .send_request(req()?, Some(HttpsNegotiator), timeout)
+                .await?;
+            let presented = response.inner.extensions().get::<PeerCertificate>();
+            let intercepted = presented.is_none_or(|presented| presented.0 != *pinned);
+            return Ok((response, Some(intercepted)));
+        }
+
+        match proxy
+            .send_request_verified(req()?, Some(HttpsNegotiator), timeout)
+            .await
+        {
+            Ok(response) => Ok((response, Some(false))),
+            Err(Error::Tls(_e)) => {
+                #[cfg(feature = "log")]
+                log::trace!("{}: Judge handshake failed: {}", proxy.as_text(), _e);
+                let response = proxy
+                    .send_request(req()?, Some(HttpsNegotiator), timeout)
+                    .await?;
+                let presented = response.inner.extensions().get::<PeerCertificate>();
+                let intercepted = self
+                    .direct
+                    .get(url)
+                    .map(|direct| presented.is_none_or(|presented| presented.0 != *direct));
+                Ok((response, intercepted))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Extracts the address a request came from out of a judge's response.
+///
+/// Judges print it either as a `REMOTE_ADDR` variable or as a JSON `origin` or `ip` field;
+/// other answers leave it unknown.
+fn exit_ip(body: &str) -> Option<Ipv4Addr> {
+    body.lines()
+        .find_map(|line| {
+            let line = line.trim().trim_start_matches('"');
+            ["REMOTE_ADDR", "origin", "ip\""]
+                .iter()
+                .find_map(|key| line.strip_prefix(key))
+        })
+        .and_then(|value| {
+            value
+                .split(|c: char| !c.is_ascii_digit() && c != '.')
+                .find_map(|part| part.parse().ok())
+        })
+}
+
+async fn to_raw_response(response: Response<Incoming>) -> crate::Result<String> {
     let mut content = String::new();
     for (k, v) in response.headers() {
         content.push_str(&k.as_str().to_uppercase());
         content.push_str(": ");
-        content.push_str(v.to_str()?);
+        content.push_str(v.to_str().map_err(anyhow::Error::from)?);
         content.push('\n');
     }
     content.push_str("\n\n");
-    if let Ok(bytes) = response.collect().await.map(|body| body.to_bytes()) {
-        let body = String::from_utf8_lossy(&bytes);
-        content.push_str(&body);
-    }
+    let bytes = response.collect().await?.to_bytes();
+    content.push_str(&String::from_utf8_lossy(&bytes));
     Ok(content)
 }
 
+/// Fails with a judge error unless the status of a response is a success.
+fn check_status(url: &str, status: StatusCode) -> crate::Result<()> {
+    if status.is_success() {
+        return Ok(());
+    }
+    Err(Error::Judge {
+        url: url.to_string(),
+        status: Some(status.as_u16()),
+        reason: status.canonical_reason().unwrap_or("Unknown reason").into(),
+    })
+}
+
+/// Validates the response of a judge and reads it, completing the timings of the exchange.
+///
+/// # Arguments
+///
+/// * `judge_url`: The URL of the judge.
+/// * `response`: The response received through the proxy, timed up to its headers.
+///
+/// # Returns
+///
+/// An error if the proxy refused the request, which ends the check. Otherwise a result
+/// containing the raw response and the timings, or an error if the body could not be read,
+/// in which case another judge may be tried.
+async fn read_judge_response(
+    judge_url: &str,
+    response: ProxyRuntimes<Response<Incoming>>,
+) -> crate::Result<crate::Result<(String, Timings)>> {
+    let status = response.inner.status();
+    check_status(judge_url, status)?;
+
+    let mut timings = response.timings;
+    let read_start = Instant::now();
+    let body = match to_raw_response(response.inner).await {
+        Ok(body) => body,
+        Err(e) => {
+            return Ok(Err(Error::Judge {
+                url: judge_url.to_string(),
+                status: Some(status.as_u16()),
+                reason: e.to_string(),
+            }))
+        }
+    };
+    // `send_request` timed the exchange up to the response headers
+    timings.total = timings
+        .total
+        .map(|total| total + read_start.elapsed().as_secs_f64());
+    Ok(Ok((body, timings)))
+}
+
+/// Checks that a proxy forwards HTTP requests and determines its anonymity.
+///
+/// # Arguments
+///
+/// * `proxy`: The proxy to check; its exit address is filled in.
+/// * `timeout`: The duration allowed for each attempt.
+/// * `max_attempts`: The number of judges to try before giving up.
+/// * `my_ip`: The public addresses of this machine, used to spot transparent proxies.
+/// * `outcome`: The outcome of the check, updated with the judge being requested.
+///
+/// # Returns
+///
+/// A result containing the detected protocol and timings, or the error of the last attempt.
 pub async fn support_http(
     proxy: &mut Proxy,
     timeout: Duration,
     max_attempts: usize,
-) -> Option<ProxyRuntimes<Protocol>> {
+    my_ip: &PublicIp,
+    outcome: &mut CheckOutcome,
+) -> crate::Result<ProxyRuntimes<Protocol>> {
     let useragent = UserAgent().fake::<&str>();
+    let mut last_error = Error::Other(anyhow::anyhow!("No attempt was made"));
     for judge_url in HTTP_JUDGES.iter().cycle().take(max_attempts) {
-        if let Ok(req) = Request::get(*judge_url)
+        let req = Request::get(*judge_url)
             .header(USER_AGENT, useragent)
             .body(Empty::<Bytes>::new())
+            .map_err(anyhow::Error::from)?;
+        outcome.judge = Some(judge_url.to_string());
+
+        let response = match proxy.send_request(req, Some(HttpNegotiator), timeout).await {
+            Ok(response) => response,
+            Err(e) => {
+                last_error = e;
+                continue;
+            }
+        };
+        let (body, timings) = match read_judge_response(judge_url, response).await? {
+            Ok(result) => result,
+            Err(e) => {
+                last_error = e;
+                continue;
+            }
+        };
+
+        proxy.exit_ip = exit_ip(&body);
+        let anonymity = if my_ip.is_in(&body) {
+            Anonymity::Transparent
+        } else if ANON_INTEREST.iter().any(|&v| body.contains(v))
+            || body.contains(&proxy.ip.to_string())
         {
-            if let Ok(response) = proxy.send_request(req, Some(HttpNegotiator), timeout).await {
-                if !response.inner.status().is_success() {
-                    return None;
-                }
-                if let Ok(body) = to_raw_response(response.inner).await {
-                    if body.contains(&my_ip().await) {
-                        return Some(ProxyRuntimes {
-                            inner: Protocol::Http(Anonymity::Transparent),
-                            runtimes: response.runtimes,
-                        });
-                    }
-
-                    if ANON_INTEREST.iter().any(|&v| body.contains(v))
-                        || body.contains(&proxy.ip.to_string())
-                    {
-                        return Some(ProxyRuntimes {
-                            inner: Protocol::Http(Anonymity::Anonymous),
-                            runtimes: response.runtimes,
-                        });
-                    }
-
-                    return Some(ProxyRuntimes {
-                        inner: Protocol::Http(Anonymity::Elite),
-                        runtimes: response.runtimes,
-                    });
+            Anonymity::Anonymous
+        } else {
+            Anonymity::Elite
+        };
+        return Ok(ProxyRuntimes {
+            inner: Protocol::Http(anonymity),
+            timings,
+        });
+    }
+    Err(last_error)
+}
+
+/// Checks that a proxy tunnels HTTPS requests without tampering with TLS.
+///
+/// # Arguments
+///
+/// * `proxy`: The proxy to check; its exit address and `tls_intercepted` are filled in.
+/// * `timeout`: The duration allowed for each attempt.
+/// * `max_attempts`: The number of judges to try before giving up.
+/// * `judge`: The judges to request and how their certificates are checked.
+/// * `outcome`: The outcome of the check, updated with the judge being requested.
+///
+/// # Returns
+///
+/// A result containing the protocol and timings, or the error of the last attempt.
+pub async fn support_https(
+    proxy: &mut Proxy,
+    timeout: Duration,
+    max_attempts: usize,
+    judge: &HttpsJudge,
+    outcome: &mut CheckOutcome,
+) -> crate::Result<ProxyRuntimes<Protocol>> {
+    let judges = judge.urls();
+    let mut last_error = Error::Other(anyhow::anyhow!("No attempt was made"));
+    for judge_url in judges.iter().cycle().take(max_attempts) {
+        outcome.judge = Some(judge_url.to_string());
+
+        let (response, intercepted) = match judge.request(proxy, judge_url, timeout).await {
+            Ok(result) => result,
+            Err(e) => {
+                last_error = e;
+                continue;
+            }
+        };
+        let (body, timings) = match read_judge_response(judge_url, response).await? {
+            Ok(result) => result,
+            Err(e) => {
+                last_error = e;
+                continue;
+            }
+        };
+
+        proxy.exit_ip = exit_ip(&body);
+        proxy.tls_intercepted = intercepted;
+        return Ok(ProxyRuntimes {
+            inner: Protocol::Https,
+            timings,
+        });
+    }
+    Err(last_error)
+}
+
+/// Downloads a payload through the proxy and measures the throughput.
+///
+/// The throughput is computed from the moment the response headers arrive, so the latency
+/// of the proxy is left out. If the timeout expires first, it is computed from the bytes
+/// received so far.
+///
+/// # Arguments
+///
+/// * `proxy`: The proxy to measure.
+/// * `protocol`: The protocol the proxy was validated for, which the payload is requested
+///   with; the scheme of `url` must match it.
+/// * `url`: The URL of the payload.
+/// * `bytes`: The number of bytes to download.
+/// * `timeout`: The duration allowed for the request and for the download.
+///
+/// # Returns
+///
+/// A result containing the throughput in bytes per second, `None` if the payload server
+/// redirected the request, e.g. from plain HTTP to HTTPS, or an error if nothing could be
+/// downloaded.
+pub async fn measure_bandwidth(
+    proxy: &mut Proxy,
+    protocol: &Protocol,
+    url: &str,
+    bytes: usize,
+    timeout: Duration,
+) -> crate::Result<Option<f64>> {
+    let req = Request::get(url)
+        .header(USER_AGENT, UserAgent().fake::<&str>())
+        .body(Empty::<Bytes>::new())
+        .map_err(anyhow::Error::from)?;
+    let mut response = match protocol {
+        Protocol::Http(_) => {
+            proxy
+                .send_request(req, Some(HttpNegotiator), timeout)
+                .await?
+        }
+        Protocol::Https => {
+            proxy
+                .send_request(req, Some(HttpsNegotiator), timeout)
+                .await?
+        }
+        _ => {
+            return Err(Error::Other(anyhow::anyhow!(
+                "{} bandwidth tests are not supported yet",
+                protocol
+            )))
+        }
+    }
+    .inner;
+    let status = response.status();
+    // Following the redirect would measure another request, and says nothing of the proxy
+    if status.is_redirection() {
+        return Ok(None);
+    }
+    check_status(url, status)?;
+
+    let start_time = Instant::now();
+    let deadline = start_time + timeout;
+    let mut read = 0;
+    while read < bytes {
+        match time::timeout_at(deadline, response.frame()).await {
+            Ok(Some(frame)) => {
+                if let Some(data) = frame?.data_ref() {
+                    read += data.len();
                 }
             }
+            Ok(None) | Err(_) => break,
         }
     }
-    None
+    let elapsed = start_time.elapsed().as_secs_f64();
+
+    if read == 0 {
+        return Err(Error::Judge {
+            url: url.to_string(),
+            status: Some(status.as_u16()),
+            reason: "Nothing was downloaded".into(),
+        });
+    }
+    Ok(Some(read as f64 / elapsed.max(f64::EPSILON)))
+}
+
+/// A payload fetched without a proxy, compared with the same payload fetched through proxies.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    url: String,                      // URL of the payload.
+    useragent: &'static str,          // User agent sent with every request.
+    sha256: String,                   // Hash of the original body, hex encoded.
+    headers: HashMap<String, String>, // Original headers by lowercase name, ignored ones left out.
+}
+
+impl Reference {
+    /// Creates a reference from a payload fetched without a proxy.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The URL of the payload.
+    /// * `headers`: The original response headers.
+    /// * `body`: The original body.
+    ///
+    /// # Returns
+    ///
+    /// A new `Reference`.
+    pub fn new(url: &str, headers: &HeaderMap, body: &[u8]) -> Self {
+        Self {
+            url: url.to_string(),
+            useragent: UserAgent().fake::<&str>(),
+            sha256: sha256(body),
+            headers: comparable_headers(headers),
+        }
+    }
+
+    /// Fetches the payload directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The URL of the payload.
+    /// * `expected_sha256`: The hex encoded SHA-256 the payload must have, if known.
+    /// * `timeout`: The duration allowed for the request.
+    ///
+    /// # Returns
+    ///
+    /// A result containing the reference, or an error if the payload cannot be fetched or
+    /// does not have the expected hash.
+    pub async fn fetch(
+        url: &str,
+        expected_sha256: Option<&str>,
+        timeout: Duration,
+    ) -> crate::Result<Self> {
+        let client =
+            Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpsConnector::new());
+        let req = Request::get(url)
+            .header(USER_AGENT, UserAgent().fake::<&str>())
+            .body(Empty::<Bytes>::new())
+            .map_err(anyhow::Error::from)?;
+        let (headers, body) = time::timeout(timeout, async {
+            let response = client.request(req).await.map_err(|e| Error::Judge {
+                url: url.to_string(),
+                status: None,
+                reason: e.to_string(),
+            })?;
+            check_status(url, response.status())?;
+            let headers = response.headers().clone();
+            Ok::<_, Error>((headers, response.collect().await?.to_bytes()))
+        })
+        .await
+        .map_err(|_| Error::Timeout)??;
+
+        let reference = Self::new(url, &headers, &body);
+        if let Some(expected) = expected_sha256 {
+            if !reference.sha256.eq_ignore_ascii_case(expected.trim()) {
+                return Err(Error::Config(format!(
+                    "{}: payload has SHA-256 {}, expected {}",
+                    url, reference.sha256, expected
+                )));
+            }
+        }
+        Ok(reference)
+    }
+
+    /// Compares a payload fetched through a proxy with the original.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers`: The response headers received through the proxy.
+    /// * `body`: The body received through the proxy.
+    ///
+    /// # Returns
+    ///
+    /// The changes the proxy made.
+    pub fn compare(&self, headers: &HeaderMap, body: &[u8]) -> Tampering {
+        let received = comparable_headers(headers);
+        let is_content = |name: &&String| CONTENT_HEADERS.contains(&name.as_str());
+
+        let mut added_headers = received
+            .keys()
+            .filter(|name| !self.headers.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut removed_headers = self
+            .headers
+            .keys()
+            .filter(is_content)
+            .filter(|name| !received.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut changed_headers = self
+            .headers
+            .iter()
+            .filter(|(name, value)| {
+                is_content(name) && received.get(*name).is_some_and(|other| other != *value)
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        added_headers.sort();
+        removed_headers.sort();
+        changed_headers.sort();
+
+        Tampering {
+            body_modified: sha256(body) != self.sha256,
+            added_headers,
+            removed_headers,
+            changed_headers,
+        }
+    }
+}
+
+/// Returns the headers compared by the tampering check by lowercase name, with repeated
+/// headers joined.
+fn comparable_headers(headers: &HeaderMap) -> HashMap<String, String> {
+    let mut comparable: HashMap<String, String> = HashMap::new();
+    for (name, value) in headers {
+        let name = name.as_str().to_lowercase();
+        if IGNORED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = String::from_utf8_lossy(value.as_bytes());
+        comparable
+            .entry(name)
+            .and_modify(|joined| {
+                joined.push_str(", ");
+                joined.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+    comparable
+}
+
+/// Returns the hex encoded SHA-256 of a body.
+fn sha256(body: &[u8]) -> String {
+    Sha256::digest(body)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Fetches a known payload through the proxy and compares it with the original.
+///
+/// # Arguments
+///
+/// * `proxy`: The proxy to check.
+/// * `reference`: The original payload.
+/// * `timeout`: The duration allowed for the request.
+///
+/// # Returns
+///
+/// A result containing the changes made by the proxy, or an error if the payload cannot be
+/// fetched through it.
+pub async fn check_tampering(
+    proxy: &mut Proxy,
+    reference: &Reference,
+    timeout: Duration,
+) -> crate::Result<Tampering> {
+    let req = Request::get(reference.url.as_str())
+        .header(USER_AGENT, reference.useragent)
+        .body(Empty::<Bytes>::new())
+        .map_err(anyhow::Error::from)?;
+    let response = proxy
+        .send_request(req, Some(HttpNegotiator), timeout)
+        .await?
+        .inner;
+    check_status(&reference.url, response.status())?;
+    let headers = response.headers().clone();
+    let body = time::timeout(timeout, response.collect())
+        .await
+        .map_err(|_| Error::Timeout)??
+        .to_bytes();
+    Ok(reference.compare(&headers, &body))
 }
@@ -1,4 +1,16 @@
-use crate::Protocol;
+use std::path::PathBuf;
+
+use crate::{
+    geolookup::{self, GeoFilter},
+    resolver::IpDetector,
+    Protocol,
+};
+
+/// Default payload of the bandwidth test, a speed test endpoint serving `{bytes}` bytes.
+pub const BANDWIDTH_URL: &str = "https://speed.cloudflare.com/__down?bytes={bytes}";
+
+/// Default payload of the tampering check, a small static page served over plain HTTP.
+pub const TAMPER_URL: &str = "http://example.com/";
 
 /// Options for configuring the proxy validating process.
 pub struct Config {
@@ -10,6 +22,61 @@ pub struct Config {
     pub types: Vec<Protocol>,
     /// Maximum number of attempts to validate a proxy.
     pub max_attempts: usize,
+    /// Maximum number of validated proxies waiting to be retrieved.
+    pub queue_size: usize,
+    /// Perform geo lookup on the exit address of each working proxy; affects performance.
+    /// Enabled automatically when `exit_geo_filter` is set.
+    pub enable_geo_lookup: bool,
+    /// Also look up the autonomous system of the exit address; requires `enable_geo_lookup`.
+    pub enable_asn_lookup: bool,
+    /// Location, download URL and refresh age of the GeoIP databases.
+    pub geolookup: geolookup::Config,
+    /// Filter proxies by the location of their exit address, or of their listening address
+    /// when the judge does not report the exit one.
+    pub exit_geo_filter: GeoFilter,
+    /// Detects the public address used to spot transparent proxies.
+    pub ip_detector: IpDetector,
+    /// Number of checks run on each proxy to measure its reliability; the first one must pass.
+    /// 1 disables the reliability mode.
+    pub reliability_checks: usize,
+    /// Pause between two reliability checks in milliseconds.
+    pub reliability_interval: u64,
+    /// Minimum share of the reliability checks a proxy must pass, between 0 and 1.
+    pub min_success_rate: f64,
+    /// Self-hosted judge of HTTPS checks, used instead of the public ones (optional).
+    pub https_judge: Option<String>,
+    /// PEM or DER certificate the HTTPS judge must present; without it, the certificate
+    /// chain of the judge is verified, and if rejected, compared with the certificate the
+    /// judge presents on a direct connection (optional).
+    pub https_judge_certificate: Option<PathBuf>,
+    /// Drop proxies replacing the certificate of the HTTPS judge, i.e. intercepting TLS.
+    pub drop_intercepted: bool,
+    /// Measure the download throughput of each working proxy; affects performance.
+    pub enable_bandwidth_test: bool,
+    /// URL of the payload downloaded by the bandwidth test; `{bytes}` is replaced by
+    /// `bandwidth_bytes`. Its scheme is replaced by the one of the validated protocol, so the
+    /// endpoint must serve both HTTP and HTTPS; HTTP proxies are left unmeasured if it
+    /// redirects plain HTTP requests.
+    pub bandwidth_url: String,
+    /// Number of bytes downloaded by the bandwidth test; the rest of the payload is skipped.
+    pub bandwidth_bytes: usize,
+    /// Drop proxies slower than this many bytes per second; implies `enable_bandwidth_test`
+    /// (optional).
+    pub min_bandwidth: Option<f64>,
+    /// Fetch a known payload through each working HTTP proxy and record the changes made to
+    /// its body and headers.
+    pub enable_tamper_check: bool,
+    /// URL of the payload of the tampering check; it must serve the same content to every
+    /// client, over plain HTTP.
+    pub tamper_url: String,
+    /// Hex encoded SHA-256 of the payload of the tampering check; validation fails if the
+    /// payload fetched without a proxy does not match it (optional).
+    pub tamper_sha256: Option<String>,
+    /// Drop proxies modifying the payload or failing to fetch it; implies
+    /// `enable_tamper_check`.
+    pub drop_tampered: bool,
+    /// Also emit proxies failing a check, without a type and with the outcome in `checks`.
+    pub report_failures: bool,
 }
 
 impl Default for Config {
@@ -19,6 +86,27 @@ impl Default for Config {
             request_timeout: 3000,
             types: Vec::new(),
             max_attempts: 1,
+            queue_size: 1000,
+            enable_geo_lookup: false,
+            enable_asn_lookup: false,
+            geolookup: geolookup::Config::default(),
+            exit_geo_filter: GeoFilter::default(),
+            ip_detector: IpDetector::default(),
+            reliability_checks: 1,
+            reliability_interval: 0,
+            min_success_rate: 1.0,
+            https_judge: None,
+            https_judge_certificate: None,
+            drop_intercepted: false,
+            enable_bandwidth_test: false,
+            bandwidth_url: BANDWIDTH_URL.to_string(),
+            bandwidth_bytes: 1_000_000,
+            min_bandwidth: None,
+            enable_tamper_check: false,
+            tamper_url: TAMPER_URL.to_string(),
+            tamper_sha256: None,
+            drop_tampered: false,
+            report_failures: false,
         }
     }
 }
@@ -13,16 +13,24 @@ use std::{
 };
 
 use hashbrown::HashSet;
-use tokio::{sync::Semaphore, task::JoinHandle, time::Instant};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::JoinHandle,
+    time::{self, Instant},
+};
 
-pub use config::Config;
+use checker::HttpsJudge;
+pub use checker::Reference;
+pub use config::{Config, BANDWIDTH_URL, TAMPER_URL};
 
 use crate::{
+    geolookup::{GeoFilter, GeoLookup},
     proxy::{
         client::ProxyClient,
-        models::{Protocol, Proxy, ProxyType},
+        models::{CheckOutcome, CheckPhase, Protocol, Proxy, ProxyType},
     },
-    resolver::my_ip,
+    resolver::PublicIp,
+    Error,
 };
 
 pub struct ProxyValidator {
@@ -33,47 +41,352 @@ pub struct ProxyValidator {
     is_finished: Arc<AtomicBool>,
 }
 
-#[allow(unused_must_use)]
+/// Geo lookup of exit addresses, shared by the validation tasks.
+struct ExitGeo {
+    geolookup: GeoLookup, // GeoIP instance for exit address lookups.
+    filter: GeoFilter,    // Filter applied to the exit location.
+}
+
+impl ExitGeo {
+    /// Looks up the exit location of a proxy and checks it against the filter.
+    ///
+    /// Proxies whose exit address is unknown are checked against their entry location.
+    fn apply(&self, proxy: &mut Proxy) -> bool {
+        if let Some(exit_ip) = &proxy.exit_ip {
+            proxy.exit_geo = Some(self.geolookup.lookup(exit_ip));
+        }
+        self.filter.matches(proxy.effective_geo())
+    }
+}
+
+/// Repeated checks catching proxies that stop working, shared by the validation tasks.
+struct Reliability {
+    checks: usize,           // Number of checks, including the first one.
+    interval: Duration,      // Pause before each extra check.
+    min_success_rate: f64,   // Share of the checks that must pass.
+    workers: Arc<Semaphore>, // Worker permits, released during the pauses.
+}
+
+impl Reliability {
+    /// Checks a proxy that passed its first check again, recording its success rate and
+    /// jitter, and a failure if it passes too few checks.
+    ///
+    /// Stops as soon as the minimum success rate can no longer be reached. The worker permit
+    /// is released during the pauses, so that other proxies are checked meanwhile.
+    async fn run(
+        &self,
+        proxy: &mut Proxy,
+        protocol: &Protocol,
+        settings: &CheckSettings,
+        outcome: &mut CheckOutcome,
+        permit: &mut Option<OwnedSemaphorePermit>,
+    ) {
+        outcome.phase = CheckPhase::Reliability;
+        // Tolerate rounding, e.g. 0.7 * 10 is slightly above 7
+        let required = (self.min_success_rate * self.checks as f64 - 1e-9).ceil() as usize;
+        let mut successes = 1;
+        let mut ran = 1;
+        let mut totals = Vec::from_iter(proxy.timings.total);
+
+        while ran < self.checks && successes + (self.checks - ran) >= required {
+            if !self.interval.is_zero() {
+                permit.take();
+                time::sleep(self.interval).await;
+                match Arc::clone(&self.workers).acquire_owned().await {
+                    Ok(acquired) => *permit = Some(acquired),
+                    Err(_) => break,
+                }
+            }
+            let mut again = proxy.clone();
+            let mut again_outcome = CheckOutcome::new(protocol.clone());
+            let result = check(&mut again, protocol, settings, &mut again_outcome).await;
+            ran += 1;
+            if result.is_ok() {
+                successes += 1;
+                totals.extend(again.timings.total);
+            }
+        }
+
+        proxy.success_rate = Some(successes as f64 / ran as f64);
+        proxy.jitter = jitter(&totals);
+        if successes < required {
+            outcome.error_kind = Some("unreliable");
+            outcome.error = Some(format!(
+                "Passed {} of {} checks, {} of {} required",
+                successes, ran, required, self.checks
+            ));
+        }
+    }
+}
+
+/// Computes the standard deviation of response times; `None` for fewer than two.
+fn jitter(times: &[f64]) -> Option<f64> {
+    if times.len() < 2 {
+        return None;
+    }
+    let mean = times.iter().sum::<f64>() / times.len() as f64;
+    let variance = times.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / times.len() as f64;
+    Some(variance.sqrt())
+}
+
+/// Bandwidth test run on working proxies, shared by the validation tasks.
+struct BandwidthTest {
+    url: String,      // URL of the payload, with `{bytes}` replaced.
+    bytes: usize,     // Number of bytes to download.
+    min: Option<f64>, // Minimum throughput in bytes per second.
+}
+
+impl BandwidthTest {
+    /// Measures the throughput of a proxy, recording a failure if it is too slow.
+    ///
+    /// The payload is requested over plain HTTP from HTTP proxies and through a tunnel from
+    /// HTTPS proxies, whatever the scheme of the configured URL. When no minimum is set,
+    /// proxies whose throughput cannot be measured are kept. A redirect from the payload
+    /// server, which may only serve HTTPS, leaves the throughput unmeasured without failing
+    /// the proxy.
+    async fn run(
+        &self,
+        proxy: &mut Proxy,
+        protocol: &Protocol,
+        timeout: Duration,
+        outcome: &mut CheckOutcome,
+    ) {
+        outcome.phase = CheckPhase::Bandwidth;
+        let scheme = match protocol {
+            Protocol::Https => "https",
+            _ => "http",
+        };
+        let url = match self.url.split_once("://") {
+            Some((_, rest)) => format!("{}://{}", scheme, rest),
+            None => format!("{}://{}", scheme, self.url),
+        };
+        match checker::measure_bandwidth(proxy, protocol, &url, self.bytes, timeout).await {
+            Ok(Some(bandwidth)) => proxy.bandwidth = Some(bandwidth),
+            Ok(None) => {
+                #[cfg(feature = "log")]
+                log::debug!("{}: Bandwidth payload was redirected, not measured", url);
+                return;
+            }
+            Err(e) => {
+                #[cfg(feature = "log")]
+                log::trace!("{}: Bandwidth test failed: {}", proxy.as_text(), e);
+                if self.min.is_some() {
+                    outcome.error_kind = Some(e.kind());
+                    outcome.error = Some(e.to_string());
+                }
+                return;
+            }
+        }
+        if let (Some(min), Some(bandwidth)) = (self.min, proxy.bandwidth) {
+            if bandwidth < min {
+                outcome.error_kind = Some("slow");
+                outcome.error = Some(format!(
+                    "Bandwidth of {:.0} B/s is below {:.0} B/s",
+                    bandwidth, min
+                ));
+            }
+        }
+    }
+}
+
+/// Tampering check run on working HTTP proxies, shared by the validation tasks.
+struct TamperCheck {
+    reference: Reference, // Payload fetched without a proxy.
+    drop: bool,           // Fail proxies modifying the payload or failing to fetch it.
+}
+
+impl TamperCheck {
+    /// Fetches the payload through a proxy and records the changes it made, and a failure
+    /// if dropping is enabled and the proxy modified it.
+    async fn run(&self, proxy: &mut Proxy, timeout: Duration, outcome: &mut CheckOutcome) {
+        outcome.phase = CheckPhase::Tampering;
+        match checker::check_tampering(proxy, &self.reference, timeout).await {
+            Ok(tampering) => {
+                if self.drop && tampering.is_tampered() {
+                    outcome.error_kind = Some("tampered");
+                    outcome.error = Some("The proxy modified the payload".into());
+                }
+                proxy.tampering = Some(tampering);
+            }
+            Err(e) => {
+                #[cfg(feature = "log")]
+                log::trace!("{}: Tampering check failed: {}", proxy.as_text(), e);
+                if self.drop {
+                    outcome.error_kind = Some(e.kind());
+                    outcome.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Settings of the protocol checks, shared by the validation tasks.
+struct CheckSettings {
+    max_attempts: usize,     // Number of judges tried before a check fails.
+    timeout: Duration,       // Duration allowed for each stage of a request.
+    my_ip: PublicIp,         // Public addresses of this machine.
+    https_judge: HttpsJudge, // Judges of HTTPS checks and how their certificates are checked.
+    drop_intercepted: bool,  // Fail proxies replacing the certificate of the HTTPS judge.
+}
+
+/// Checks that a proxy supports the given protocol, filling in its type and timings.
+///
+/// The stage reached, the judge requested and the timings are recorded in `outcome`.
+async fn check(
+    proxy: &mut Proxy,
+    protocol: &Protocol,
+    settings: &CheckSettings,
+    outcome: &mut CheckOutcome,
+) -> crate::Result<()> {
+    outcome.phase = CheckPhase::Connect;
+    let tcp = proxy.connect_timeout(settings.timeout).await?;
+    outcome.timings.merge(&tcp.timings);
+    tcp.apply(proxy);
+
+    outcome.phase = CheckPhase::Request;
+    let result = match protocol {
+        Protocol::Http(_) => {
+            checker::support_http(
+                proxy,
+                settings.timeout,
+                settings.max_attempts,
+                &settings.my_ip,
+                outcome,
+            )
+            .await?
+        }
+        Protocol::Https => {
+            checker::support_https(
+                proxy,
+                settings.timeout,
+                settings.max_attempts,
+                &settings.https_judge,
+                outcome,
+            )
+            .await?
+        }
+        _ => {
+            return Err(Error::Other(anyhow::anyhow!(
+                "{} checks are not supported yet",
+                protocol
+            )))
+        }
+    };
+    outcome.timings.merge(&result.timings);
+    result.apply(proxy);
+    proxy.proxy_type = Some(ProxyType::checked(result.inner));
+    Ok(())
+}
+
+/// Records a failed check in its outcome, refining the stage from the kind of error.
+fn fail(outcome: &mut CheckOutcome, error: &Error) {
+    match error {
+        Error::Negotiation { .. } | Error::AuthRequired { .. } => {
+            outcome.phase = CheckPhase::Negotiate
+        }
+        Error::Judge { .. } => outcome.phase = CheckPhase::Judge,
+        _ => {}
+    }
+    outcome.error_kind = Some(error.kind());
+    outcome.error = Some(error.to_string());
+}
+
+#[allow(unused_must_use, clippy::too_many_arguments)]
 async fn do_work(
     mut proxy: Proxy,
     sender: kanal::AsyncSender<Proxy>,
     counter: Arc<AtomicUsize>,
     protocol: Protocol,
-    max_attempts: usize,
-    timeout: u64,
+    settings: Arc<CheckSettings>,
+    reliability: Option<Arc<Reliability>>,
+    exit_geo: Option<Arc<ExitGeo>>,
+    tamper_check: Option<Arc<TamperCheck>>,
+    bandwidth_test: Option<Arc<BandwidthTest>>,
+    report_failures: bool,
+    permit: OwnedSemaphorePermit,
 ) {
-    let timeout = Duration::from_secs(timeout);
-    if let Ok(tcp) = proxy.connect_timeout(timeout).await {
-        tcp.apply(&mut proxy);
-
-        if let Protocol::Http(_) = protocol {
-            if let Some(result) = checker::support_http(&mut proxy, timeout, max_attempts).await {
-                result.apply(&mut proxy);
-                proxy.proxy_type = Some(ProxyType::checked(result.inner));
-            }
+    let mut permit = Some(permit);
+    let mut outcome = CheckOutcome::new(protocol.clone());
+    // Each stage runs only if the previous ones passed
+    if let Err(e) = check(&mut proxy, &protocol, &settings, &mut outcome).await {
+        fail(&mut outcome, &e);
+    }
+    if outcome.is_ok() && settings.drop_intercepted && proxy.tls_intercepted == Some(true) {
+        outcome.phase = CheckPhase::Filter;
+        outcome.error_kind = Some("intercepted");
+        outcome.error = Some("The certificate of the judge was replaced".into());
+    }
+    if let (true, Some(exit_geo)) = (outcome.is_ok(), &exit_geo) {
+        outcome.phase = CheckPhase::Filter;
+        if !exit_geo.apply(&mut proxy) {
+            outcome.error_kind = Some("filtered");
+            outcome.error = Some("Exit location does not match the filter".into());
         }
+    }
+    if let (true, Some(reliability)) = (outcome.is_ok(), &reliability) {
+        reliability
+            .run(&mut proxy, &protocol, &settings, &mut outcome, &mut permit)
+            .await;
+    }
+    // Tunnels forward the payload untouched, so only plain HTTP is checked
+    if let (true, Protocol::Http(_), Some(tamper_check)) =
+        (outcome.is_ok(), &protocol, &tamper_check)
+    {
+        tamper_check
+            .run(&mut proxy, settings.timeout, &mut outcome)
+            .await;
+    }
+    if let (true, Some(bandwidth_test)) = (outcome.is_ok(), &bandwidth_test) {
+        bandwidth_test
+            .run(&mut proxy, &protocol, settings.timeout, &mut outcome)
+            .await;
+    }
+    if outcome.is_ok() {
+        outcome.phase = CheckPhase::Done;
+    }
 
-        if proxy.proxy_type.is_some() {
-            #[cfg(feature = "log")]
-            log::trace!(
-                "{}: support protocol: {}",
-                proxy.as_text(),
-                proxy.proxy_type.as_ref().unwrap().protocol
-            );
+    if !outcome.is_ok() {
+        #[cfg(feature = "log")]
+        log::trace!(
+            "{}: {} check failed at {}: {}",
+            proxy.as_text(),
+            protocol,
+            outcome.phase,
+            outcome.error.as_deref().unwrap_or_default()
+        );
+        if report_failures {
+            proxy.proxy_type = None;
+            proxy.checks.push(outcome);
             sender.send(proxy).await.unwrap_or_default();
-            counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         }
+        return;
+    }
+
+    if let Some(_proxy_type) = &proxy.proxy_type {
+        #[cfg(feature = "log")]
+        log::trace!(
+            "{}: support protocol: {}",
+            proxy.as_text(),
+            _proxy_type.protocol
+        );
+        if report_failures {
+            proxy.checks.push(outcome);
+        }
+        sender.send(proxy).await.unwrap_or_default();
+        counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     }
 }
 
 impl ProxyValidator {
     #[allow(unused_must_use)]
-    pub async fn validate<I>(proxy_source: I, config: Config) -> anyhow::Result<Self>
+    pub async fn validate<I>(proxy_source: I, config: Config) -> crate::Result<Self>
     where
         I: Iterator<Item = Proxy> + Send + 'static,
     {
         if config.types.is_empty() {
-            anyhow::bail!("config.types cannot be empty; please specify at least one type.");
+            return Err(Error::Config(
+                "config.types cannot be empty; please specify at least one type.".into(),
+            ));
         }
 
         #[cfg(feature = "log")]
@@ -82,9 +395,72 @@ impl ProxyValidator {
             config.concurrency_limit
         );
 
-        my_ip().await;
+        let timeout = Duration::from_secs(config.request_timeout);
+        let certificate = match &config.https_judge_certificate {
+            Some(path) => Some(checker::read_certificate(path)?),
+            None => None,
+        };
+        // Only HTTPS checks need the certificates of the judges
+        let https_judge = if config.types.contains(&Protocol::Https) {
+            HttpsJudge::new(config.https_judge.clone(), certificate, timeout).await
+        } else {
+            HttpsJudge::default()
+        };
+        let settings = Arc::new(CheckSettings {
+            max_attempts: config.max_attempts,
+            timeout,
+            my_ip: config.ip_detector.detect().await?,
+            https_judge,
+            drop_intercepted: config.drop_intercepted,
+        });
+
+        let exit_geo = if config.enable_geo_lookup || !config.exit_geo_filter.is_empty() {
+            let mut geolookup = GeoLookup::with_config(config.geolookup.clone()).await?;
+            if config.enable_asn_lookup || config.exit_geo_filter.uses_asn() {
+                geolookup.enable_asn().await?;
+            }
+            Some(Arc::new(ExitGeo {
+                geolookup,
+                filter: config.exit_geo_filter.clone(),
+            }))
+        } else {
+            None
+        };
+
+        let sem = Arc::new(Semaphore::new(config.concurrency_limit));
+        let reliability = (config.reliability_checks > 1).then(|| {
+            Arc::new(Reliability {
+                checks: config.reliability_checks,
+                interval: Duration::from_millis(config.reliability_interval),
+                min_success_rate: config.min_success_rate,
+                workers: Arc::clone(&sem),
+            })
+        });
+        let tamper_check = if config.enable_tamper_check || config.drop_tampered {
+            Some(Arc::new(TamperCheck {
+                reference: Reference::fetch(
+                    &config.tamper_url,
+                    config.tamper_sha256.as_deref(),
+                    settings.timeout,
+                )
+                .await?,
+                drop: config.drop_tampered,
+            }))
+        } else {
+            None
+        };
+        let bandwidth_test =
+            (config.enable_bandwidth_test || config.min_bandwidth.is_some()).then(|| {
+                Arc::new(BandwidthTest {
+                    url: config
+                        .bandwidth_url
+                        .replace("{bytes}", &config.bandwidth_bytes.to_string()),
+                    bytes: config.bandwidth_bytes,
+                    min: config.min_bandwidth,
+                })
+            });
 
-        let (sender, receiver) = kanal::unbounded_async();
+        let (sender, receiver) = kanal::bounded_async(config.queue_size);
         let validator = Self {
             receiver: receiver.to_sync(),
             total: Arc::new(AtomicUsize::new(0)),
@@ -97,7 +473,6 @@ impl ProxyValidator {
         let total = Arc::clone(&validator.total);
         let is_finished = Arc::clone(&validator.is_finished);
         tokio::spawn(async move {
-            let sem = Arc::new(Semaphore::new(config.concurrency_limit));
             for mut proxy in proxy_source {
                 if is_finished.load(std::sync::atomic::Ordering::Relaxed) {
                     break;
@@ -108,26 +483,41 @@ impl ProxyValidator {
                     if config
                         .types
                         .iter()
-                        .any(|right_proto| match (&protocol, right_proto) {
-                            (Protocol::Http(_), Protocol::Http(_))
-                            | (Protocol::Connect(_), Protocol::Connect(_)) => true,
-                            _ => protocol == *right_proto,
-                        })
+                        .any(|right| protocol.is_same_kind(right))
                     {
                         if !added {
                             total.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                             added = true;
                         }
-                        let permit = Arc::clone(&sem);
+                        // Wait for a free worker before spawning, so pending checks don't pile up
+                        let Ok(permit) = Arc::clone(&sem).acquire_owned().await else {
+                            return;
+                        };
                         let sender = sender.clone();
                         let counter = Arc::clone(&counter);
-                        let max_attempts = config.max_attempts;
-                        let timeout = config.request_timeout;
+                        let settings = Arc::clone(&settings);
                         let proxy = proxy.clone();
+                        let reliability = reliability.clone();
+                        let exit_geo = exit_geo.clone();
+                        let tamper_check = tamper_check.clone();
+                        let bandwidth_test = bandwidth_test.clone();
+                        let report_failures = config.report_failures;
 
                         tokio::spawn(async move {
-                            let _ = permit.acquire().await.unwrap();
-                            do_work(proxy, sender, counter, protocol, max_attempts, timeout).await
+                            do_work(
+                                proxy,
+                                sender,
+                                counter,
+                                protocol,
+                                settings,
+                                reliability,
+                                exit_geo,
+                                tamper_check,
+                                bandwidth_test,
+                                report_failures,
+                                permit,
+                            )
+                            .await;
                         });
                     }
                 }
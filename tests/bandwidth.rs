@@ -0,0 +1,147 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{ok_response, proxy_at, read_request, JUDGE_BODY};
+use fluxy::{
+    proxy::models::{Anonymity, CheckPhase, Protocol, Proxy},
+    validator::Config,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+    time::sleep,
+};
+
+const PAYLOAD_URL: &str = "http://payload.test/?bytes={bytes}";
+const BYTES: usize = 100_000;
+const CHUNK: usize = 10_000;
+const CHUNK_DELAY: Duration = Duration::from_millis(20);
+
+/// How the fake proxy answers payload requests.
+#[derive(Clone, Copy)]
+enum Payload {
+    Served,     // Payload sent in chunks, `CHUNK_DELAY` apart.
+    Missing,    // 404 response.
+    Redirected, // Redirect to the HTTPS payload, as servers only serving HTTPS do.
+}
+
+/// Answers judge requests at once and payload requests as configured.
+async fn serve(stream: &mut TcpStream, payload: Payload) {
+    // Connection checks close without a request
+    let Some(request_line) = read_request(stream).await else {
+        return;
+    };
+    if !request_line.contains("payload.test") {
+        let response = ok_response(JUDGE_BODY);
+        stream.write_all(response.as_bytes()).await.unwrap();
+        return;
+    }
+
+    let refusal: Option<&[u8]> = match payload {
+        Payload::Served => None,
+        Payload::Missing => Some(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"),
+        Payload::Redirected => Some(
+            b"HTTP/1.1 301 Moved Permanently\r\nLocation: https://payload.test/\r\n\
+              Content-Length: 0\r\n\r\n",
+        ),
+    };
+    if let Some(response) = refusal {
+        stream.write_all(response).await.unwrap();
+        return;
+    }
+    assert!(request_line.contains(&format!("bytes={}", BYTES)));
+    let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", BYTES);
+    stream.write_all(head.as_bytes()).await.unwrap();
+    for _ in 0..BYTES / CHUNK {
+        sleep(CHUNK_DELAY).await;
+        if stream.write_all(&[b'0'; CHUNK]).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Starts an HTTP proxy serving every request itself.
+async fn proxy(payload: Payload) -> Proxy {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move { serve(&mut stream, payload).await });
+        }
+    });
+    proxy_at(address, vec![Protocol::Http(Anonymity::Unknown)])
+}
+
+/// Validates a proxy with the bandwidth test and returns the emitted result.
+async fn validate(proxy: Proxy, min_bandwidth: Option<f64>) -> Proxy {
+    let config = Config {
+        enable_bandwidth_test: true,
+        bandwidth_url: PAYLOAD_URL.to_string(),
+        bandwidth_bytes: BYTES,
+        min_bandwidth,
+        ..common::config(vec![Protocol::Http(Anonymity::Unknown)])
+    };
+    common::validate_one(proxy, config).await
+}
+
+/// Throughput of the payload, which takes at least `BYTES / CHUNK` chunk delays to arrive.
+fn max_bandwidth() -> f64 {
+    BYTES as f64 / (CHUNK_DELAY * (BYTES / CHUNK) as u32).as_secs_f64()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn bandwidth_is_measured_from_the_payload() {
+    let result = validate(proxy(Payload::Served).await, None).await;
+
+    let bandwidth = result.bandwidth.expect("bandwidth was not measured");
+    assert!(bandwidth <= max_bandwidth(), "measured {} B/s", bandwidth);
+    assert!(
+        bandwidth > max_bandwidth() / 10.0,
+        "measured {} B/s",
+        bandwidth
+    );
+    assert!(result.proxy_type.is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn proxies_above_the_minimum_are_kept() {
+    let result = validate(proxy(Payload::Served).await, Some(max_bandwidth() / 10.0)).await;
+
+    assert!(result.proxy_type.is_some());
+    assert_eq!(result.checks[0].phase, CheckPhase::Done);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn proxies_below_the_minimum_are_dropped() {
+    let result = validate(proxy(Payload::Served).await, Some(max_bandwidth() * 2.0)).await;
+
+    assert!(result.proxy_type.is_none());
+    assert!(result.bandwidth.is_some());
+    let outcome = &result.checks[0];
+    assert_eq!(outcome.phase, CheckPhase::Bandwidth);
+    assert_eq!(outcome.error_kind, Some("slow"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unmeasured_proxies_are_dropped_only_with_a_minimum() {
+    let kept = validate(proxy(Payload::Missing).await, None).await;
+    assert!(kept.proxy_type.is_some());
+    assert_eq!(kept.bandwidth, None);
+
+    let dropped = validate(proxy(Payload::Missing).await, Some(1.0)).await;
+    assert!(dropped.proxy_type.is_none());
+    let outcome = &dropped.checks[0];
+    assert_eq!(outcome.phase, CheckPhase::Bandwidth);
+    assert_eq!(outcome.error_kind, Some("judge"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn redirected_payloads_are_not_measured() {
+    let result = validate(proxy(Payload::Redirected).await, Some(1.0)).await;
+
+    assert!(result.proxy_type.is_some());
+    assert_eq!(result.bandwidth, None);
+    assert_eq!(result.checks[0].phase, CheckPhase::Done);
+}
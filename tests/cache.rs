@@ -0,0 +1,104 @@
+use std::{env, fs, path::PathBuf, sync::Arc, time::Duration};
+
+use fluxy::providers::{
+    cache::SourceCache,
+    models::{FetchOptions, Source},
+    IProxyTrait,
+};
+use http_body_util::Empty;
+use hyper::body::Bytes;
+use hyper_tls::HttpsConnector;
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+
+const URL: &str = "https://example.test/proxies.txt";
+
+/// Creates an empty cache directory unique to the test.
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("fluxy-cache-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// A provider relying on the default fetch.
+struct Site;
+
+impl IProxyTrait for Site {
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        Ok(vec![])
+    }
+}
+
+#[test]
+fn written_sources_are_read_back() {
+    let dir = cache_dir("read");
+    let cache = SourceCache::new(dir.clone()).unwrap();
+
+    cache
+        .write(URL, URL, Some("\"v1\"".to_string()), None, "10.0.0.1:8080")
+        .unwrap();
+
+    assert_eq!(cache.read(URL).as_deref(), Some("10.0.0.1:8080"));
+    let entry = cache.entry(URL).unwrap();
+    assert_eq!(entry.etag.as_deref(), Some("\"v1\""));
+    assert_eq!(entry.final_url, None);
+    assert!(cache.entry("https://example.test/other.txt").is_none());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn rewrites_replace_the_cached_files() {
+    let dir = cache_dir("rewrite");
+    let cache = SourceCache::new(dir.clone()).unwrap();
+    let moved = "https://mirror.test/proxies.txt";
+
+    cache
+        .write(URL, URL, Some("\"v1\"".to_string()), None, "10.0.0.1:8080")
+        .unwrap();
+    cache
+        .write(URL, moved, None, None, "10.0.0.2:8080")
+        .unwrap();
+
+    assert_eq!(cache.read(URL).as_deref(), Some("10.0.0.2:8080"));
+    let entry = cache.entry(URL).unwrap();
+    assert_eq!(entry.etag, None);
+    assert_eq!(entry.final_url.as_deref(), Some(moved));
+    // Only the body and its metadata remain, no temporary files
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn files_of_another_url_are_a_miss() {
+    let dir = cache_dir("collision");
+    let cache = SourceCache::new(dir.clone()).unwrap();
+    cache.write(URL, URL, None, None, "10.0.0.1:8080").unwrap();
+    // Stand in for a URL whose key collides with `URL`
+    let entry_path = fs::read_dir(&dir)
+        .unwrap()
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .unwrap();
+    let content = fs::read_to_string(&entry_path).unwrap();
+    fs::write(&entry_path, content.replace(URL, "https://other.test/")).unwrap();
+    let options = FetchOptions {
+        cache: Some(Arc::new(cache)),
+        offline: true,
+        ..Default::default()
+    };
+    let client =
+        Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpsConnector::new());
+
+    let cache = options.cache.as_deref().unwrap();
+    assert!(cache.entry(URL).is_none());
+    assert_eq!(cache.read(URL), None);
+    let error = Site
+        .fetch(Arc::new(client), URL, Duration::from_secs(5), &options)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("not cached"), "got {}", error);
+    fs::remove_dir_all(dir).unwrap();
+}
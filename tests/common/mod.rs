@@ -0,0 +1,90 @@
+//! Helpers shared by the integration tests: local servers and the validator harness.
+#![allow(dead_code)] // Each test binary uses only some of the helpers
+
+use std::net::SocketAddr;
+
+use fluxy::{
+    proxy::models::{Protocol, Proxy},
+    resolver::IpDetector,
+    validator::{Config, ProxyValidator},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Body of a judge answer, reporting an exit address other than ours.
+pub const JUDGE_BODY: &str = "REMOTE_ADDR = 5.6.7.8";
+
+/// Reads an HTTP request up to the end of its headers and returns its head.
+///
+/// Returns `None` if the connection closed first, e.g. for the validator's connection checks.
+pub async fn read_head<S: AsyncRead + Unpin>(stream: &mut S) -> Option<String> {
+    let mut request = vec![];
+    let mut chunk = [0; 256];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        request.extend_from_slice(&chunk[..read]);
+    }
+    Some(String::from_utf8_lossy(&request).into_owned())
+}
+
+/// Reads an HTTP request up to the end of its headers and returns its request line.
+///
+/// Returns `None` if the connection closed first, e.g. for the validator's connection checks.
+pub async fn read_request<S: AsyncRead + Unpin>(stream: &mut S) -> Option<String> {
+    let head = read_head(stream).await?;
+    head.lines().next().map(ToString::to_string)
+}
+
+/// Builds a `200 OK` response carrying the given body.
+pub fn ok_response(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// Creates a proxy listening at a local address, expected to support the given protocols.
+pub fn proxy_at(address: SocketAddr, expected_types: Vec<Protocol>) -> Proxy {
+    let SocketAddr::V4(address) = address else {
+        unreachable!("listeners are bound to IPv4");
+    };
+    Proxy {
+        ip: *address.ip(),
+        port: address.port(),
+        expected_types,
+        ..Default::default()
+    }
+}
+
+/// Returns a validator configuration checking the given protocols offline: no geo lookup, a
+/// fixed public address, and failures reported.
+pub fn config(types: Vec<Protocol>) -> Config {
+    Config {
+        request_timeout: 5,
+        types,
+        enable_geo_lookup: false,
+        ip_detector: IpDetector::fixed(&["10.9.9.9".parse().unwrap()]),
+        report_failures: true,
+        ..Default::default()
+    }
+}
+
+/// Validates proxies and returns every emitted result.
+pub async fn validate(proxies: Vec<Proxy>, config: Config) -> Vec<Proxy> {
+    let validator = ProxyValidator::validate(proxies.into_iter(), config)
+        .await
+        .unwrap();
+    tokio::task::spawn_blocking(move || validator.collect::<Vec<_>>())
+        .await
+        .unwrap()
+}
+
+/// Validates a single proxy and returns its only result.
+pub async fn validate_one(proxy: Proxy, config: Config) -> Proxy {
+    let mut results = validate(vec![proxy], config).await;
+    assert_eq!(results.len(), 1);
+    results.pop().unwrap()
+}
@@ -0,0 +1,155 @@
+use std::{borrow::Cow, net::SocketAddrV4};
+
+use fluxy::providers::{
+    decoders::{decode, extract_addresses, Base64Decoder, Decoder, HexDecoder, JsVariableDecoder},
+    models::Source,
+    IProxyTrait,
+};
+
+fn addresses(text: &str) -> Vec<String> {
+    extract_addresses(text)
+        .iter()
+        .map(SocketAddrV4::to_string)
+        .collect()
+}
+
+#[test]
+fn extracts_addresses_from_plain_lists_and_markup() {
+    let text = "1.2.3.4:80\n  5.6.7.8:3128 \n<td>9.10.11.12:8080</td>\n\
+                999.1.1.1:80\n1.2.3.4\n11.22.33.44:99999\n10.0.0.1:1080:user:pass";
+    assert_eq!(
+        addresses(text),
+        [
+            "1.2.3.4:80",
+            "5.6.7.8:3128",
+            "9.10.11.12:8080",
+            "10.0.0.1:1080"
+        ]
+    );
+}
+
+#[test]
+fn decodes_base64_fixture() {
+    let html = include_str!("fixtures/base64.html");
+    assert!(addresses(html).is_empty());
+
+    assert_eq!(
+        addresses(&Base64Decoder.decode(html)),
+        [
+            "103.152.112.162:80",
+            "45.77.56.114:3128",
+            "188.166.56.246:8080"
+        ]
+    );
+}
+
+#[test]
+fn decodes_hex_fixture() {
+    let html = include_str!("fixtures/hex.html");
+    assert!(addresses(html).is_empty());
+
+    assert_eq!(
+        addresses(&HexDecoder.decode(html)),
+        [
+            "51.158.68.133:8811",
+            "139.59.1.14:3128",
+            "92.205.22.114:9090"
+        ]
+    );
+}
+
+#[test]
+fn decodes_js_variables_fixture() {
+    let html = include_str!("fixtures/js_variables.html");
+    assert!(addresses(html).is_empty());
+
+    assert_eq!(
+        addresses(&JsVariableDecoder.decode(html)),
+        [
+            "103.152.112.162:80",
+            "45.77.56.114:3128",
+            "188.166.56.246:8080",
+            "20.210.113.32:8123"
+        ]
+    );
+}
+
+#[test]
+fn chains_decoders_in_order() {
+    let html = format!(
+        "{}{}",
+        include_str!("fixtures/base64.html"),
+        include_str!("fixtures/js_variables.html")
+    );
+    let decoders: Vec<Box<dyn Decoder>> =
+        vec![Box::new(Base64Decoder), Box::new(JsVariableDecoder)];
+    assert_eq!(addresses(&decode(&html, &decoders)).len(), 7);
+}
+
+#[test]
+fn leaves_unrelated_literals_untouched() {
+    let html = r#"<a title="test" href="/page/2">next</a><script>var note = "abcd";</script>"#;
+    assert_eq!(Base64Decoder.decode(html), html);
+    assert_eq!(HexDecoder.decode(html), html);
+    assert_eq!(JsVariableDecoder.decode(html), html);
+}
+
+#[test]
+fn js_arithmetic_follows_javascript() {
+    let html = "<script>var a=16160;var b=7/2;var c=b|0;var d=-7.5%2</script>\
+                <script>document.write(\"1.2.3.4:\"+(a/2))</script>\
+                <script>document.write(\" \"+b+\" \"+c+\" \"+d+\" \"+(0x10^1.9))</script>\
+                <script>document.write(\" \"+(a/0))</script>";
+    // Division by zero has no sensible text, so its script is kept
+    assert_eq!(
+        JsVariableDecoder.decode(html),
+        "<script>var a=16160;var b=7/2;var c=b|0;var d=-7.5%2</script>\
+         1.2.3.4:8080 3.5 3 -1.5 17<script>document.write(\" \"+(a/0))</script>"
+    );
+}
+
+struct PlainProvider;
+
+impl IProxyTrait for PlainProvider {
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        Ok(vec![])
+    }
+}
+
+struct DecodingProvider;
+
+impl IProxyTrait for DecodingProvider {
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        Ok(vec![])
+    }
+
+    fn decoders(&self) -> Vec<Box<dyn Decoder>> {
+        vec![Box::new(JsVariableDecoder)]
+    }
+}
+
+async fn scraped(provider: &(dyn IProxyTrait + Sync), content: &'static str) -> Vec<String> {
+    provider
+        .scrape(Cow::Borrowed(content), vec![])
+        .await
+        .unwrap()
+        .iter()
+        .map(|proxy| proxy.as_text().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn only_decoding_providers_scan_markup() {
+    let content = "1.2.3.4:80\n <td>5.6.7.8:3128</td>\n9.10.11.12:8080 13.14.15.16:8080";
+
+    assert_eq!(scraped(&PlainProvider, content).await, ["1.2.3.4:80"]);
+    assert_eq!(
+        scraped(&DecodingProvider, content).await,
+        [
+            "1.2.3.4:80",
+            "5.6.7.8:3128",
+            "9.10.11.12:8080",
+            "13.14.15.16:8080"
+        ]
+    );
+}
@@ -0,0 +1,259 @@
+use std::{borrow::Cow, env, fs, path::PathBuf, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use fluxy::{
+    fetcher::{Config, DedupMode, ProxyFetcher, SourceStats},
+    providers::{
+        models::{FetchOptions, Source, SourceContent},
+        IProxyTrait,
+    },
+    proxy::models::{Protocol, Proxy},
+};
+use http_body_util::Empty;
+use hyper::body::Bytes;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+
+const FIRST: &str = "http://first.test/";
+const SECOND: &str = "http://second.test/";
+
+/// A provider serving fixed lists; each list is served 100 ms after the previous one, so
+/// sightings arrive in the order of the lists.
+struct ListProvider {
+    lists: Vec<(&'static str, Protocol, &'static str)>, // URL, protocol and body of each list.
+}
+
+#[async_trait]
+impl IProxyTrait for ListProvider {
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        self.lists
+            .iter()
+            .map(|(url, protocol, _)| Source::new(url, vec![protocol.clone()]))
+            .collect()
+    }
+
+    async fn fetch(
+        &self,
+        _client: Arc<Client<HttpsConnector<HttpConnector>, Empty<Bytes>>>,
+        url: &str,
+        _timeout: Duration,
+        _options: &FetchOptions,
+    ) -> anyhow::Result<SourceContent> {
+        let position = self.lists.iter().position(|(list, ..)| *list == url);
+        let (_, _, body) = self.lists[position.unwrap()];
+        tokio::time::sleep(Duration::from_millis(100) * position.unwrap() as u32).await;
+        Ok(SourceContent {
+            url: url.to_string(),
+            status: Some(200),
+            bytes: body.len(),
+            from_cache: false,
+            body: Cow::Borrowed(body),
+        })
+    }
+}
+
+/// The lists used by most tests: `10.0.0.1:8080` is listed by both, with another protocol.
+fn lists() -> Vec<(&'static str, Protocol, &'static str)> {
+    vec![
+        (FIRST, Protocol::Socks5, "10.0.0.1:8080\n10.0.0.2:8080"),
+        (SECOND, Protocol::Https, "10.0.0.1:8080\n10.0.0.1:3128"),
+    ]
+}
+
+fn config(lists: Vec<(&'static str, Protocol, &'static str)>) -> Config {
+    Config {
+        enable_geo_lookup: false,
+        use_cache: false,
+        use_providers: false,
+        providers: vec![Arc::new(ListProvider { lists })],
+        ..Default::default()
+    }
+}
+
+/// Fetches every proxy and returns them with the statistics of each source.
+async fn fetch(config: Config) -> (Vec<Proxy>, Vec<SourceStats>) {
+    let fetcher = ProxyFetcher::gather(config).await.unwrap();
+    tokio::task::spawn_blocking(move || {
+        let mut fetcher = fetcher;
+        let proxies = fetcher.by_ref().collect::<Vec<_>>();
+        (proxies, fetcher.stats())
+    })
+    .await
+    .unwrap()
+}
+
+fn addresses(proxies: &[Proxy]) -> Vec<String> {
+    proxies
+        .iter()
+        .map(|proxy| proxy.as_text().to_string())
+        .collect()
+}
+
+fn source_urls(proxy: &Proxy) -> Vec<&str> {
+    proxy
+        .sources
+        .iter()
+        .map(|origin| origin.url.as_str())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn streaming_returns_each_address_once() {
+    let (proxies, stats) = fetch(config(lists())).await;
+
+    assert_eq!(
+        addresses(&proxies),
+        ["10.0.0.1:8080", "10.0.0.2:8080", "10.0.0.1:3128"]
+    );
+    // The first sighting keeps its own protocols and source
+    assert_eq!(proxies[0].expected_types, [Protocol::Socks5]);
+    assert_eq!(source_urls(&proxies[0]), [FIRST]);
+    assert_eq!(stats[0].duplicates, 0);
+    assert_eq!(stats[1].duplicates, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn merging_adds_the_protocols_and_sources_of_later_sightings() {
+    let config = Config {
+        merge_duplicates: true,
+        ..config(lists())
+    };
+
+    let (proxies, stats) = fetch(config).await;
+
+    assert_eq!(
+        addresses(&proxies),
+        ["10.0.0.1:8080", "10.0.0.2:8080", "10.0.0.1:3128"]
+    );
+    assert_eq!(
+        proxies[0].expected_types,
+        [Protocol::Socks5, Protocol::Https]
+    );
+    assert_eq!(source_urls(&proxies[0]), [FIRST, SECOND]);
+    assert_eq!(stats[1].duplicates, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ip_mode_keeps_one_port_per_address() {
+    let config = Config {
+        dedup: DedupMode::Ip,
+        merge_duplicates: true,
+        ..config(lists())
+    };
+
+    let (proxies, stats) = fetch(config).await;
+
+    assert_eq!(addresses(&proxies), ["10.0.0.1:8080", "10.0.0.2:8080"]);
+    // The sighting on port 3128 adds nothing, its protocol was seen on another port
+    assert_eq!(
+        proxies[0].expected_types,
+        [Protocol::Socks5, Protocol::Https]
+    );
+    assert_eq!(stats[1].duplicates, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn none_mode_keeps_every_sighting() {
+    let config = Config {
+        dedup: DedupMode::None,
+        ..config(lists())
+    };
+
+    let (proxies, stats) = fetch(config).await;
+
+    assert_eq!(
+        addresses(&proxies),
+        [
+            "10.0.0.1:8080",
+            "10.0.0.2:8080",
+            "10.0.0.1:8080",
+            "10.0.0.1:3128"
+        ]
+    );
+    assert_eq!(proxies[2].expected_types, [Protocol::Https]);
+    assert!(stats.iter().all(|stats| stats.duplicates == 0));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stats_count_each_distinct_proxy_once() {
+    let lists = vec![
+        (
+            FIRST,
+            Protocol::Socks5,
+            "10.0.0.1:8080\n10.0.0.1:8080\n10.0.0.2:8080",
+        ),
+        (SECOND, Protocol::Https, "10.0.0.1:8080\n10.0.0.3:8080"),
+    ];
+
+    let (proxies, stats) = fetch(config(lists)).await;
+
+    // Repeats within a source are not parsed twice, repeats across sources are duplicates
+    assert_eq!(
+        stats
+            .iter()
+            .map(|stats| (stats.url.as_str(), stats.parsed, stats.duplicates))
+            .collect::<Vec<_>>(),
+        [(FIRST, 2, 0), (SECOND, 2, 1)]
+    );
+    let kept: usize = stats
+        .iter()
+        .map(|stats| stats.parsed - stats.duplicates)
+        .sum();
+    assert_eq!(kept, proxies.len());
+    assert!(stats.iter().all(|stats| stats.is_ok() && stats.pages == 1));
+    assert_eq!(stats[0].status, Some(200));
+    assert_eq!(stats[0].bytes, 41);
+}
+
+/// Writes a seed file unique to the test.
+fn seed(name: &str, content: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("fluxy-seed-{}-{}", name, std::process::id()));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn seeded_proxies_are_skipped_in_every_format() {
+    let path = seed(
+        "formats",
+        "[\n{\"ip\": \"10.0.0.1\", \"port\": 8080, \"protocols\": []},\n\
+         {\"ip\": \"not an address\", \"port\": 3128}\n]\n\
+         socks5://10.0.0.2:8080 | 0.5s\n",
+    );
+    let config = Config {
+        seed: Some(path.clone()),
+        ..config(lists())
+    };
+
+    let (proxies, stats) = fetch(config).await;
+
+    assert_eq!(addresses(&proxies), ["10.0.0.1:3128"]);
+    assert_eq!(stats[0].duplicates, 2);
+    assert_eq!(stats[1].duplicates, 1);
+    fs::remove_file(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn seed_keys_follow_the_dedup_mode() {
+    let path = seed("ip", "10.0.0.1:1080\n");
+    let config = Config {
+        dedup: DedupMode::Ip,
+        seed: Some(path.clone()),
+        ..config(lists())
+    };
+
+    let (proxies, _) = fetch(config).await;
+
+    assert_eq!(addresses(&proxies), ["10.0.0.2:8080"]);
+    fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn missing_seed_fails_the_gathering() {
+    let config = Config {
+        seed: Some(env::temp_dir().join("fluxy-seed-missing")),
+        ..config(lists())
+    };
+
+    assert!(ProxyFetcher::gather(config).await.is_err());
+}
@@ -0,0 +1,119 @@
+use std::io::Write;
+
+use flate2::{
+    write::{DeflateEncoder, GzEncoder, ZlibEncoder},
+    Compression,
+};
+use fluxy::providers::encoding::{decode_charset, decompress, MAX_DECOMPRESSED_SIZE};
+
+const LIST: &[u8] = b"10.0.0.1:8080\n10.0.0.2:3128\n";
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], Compression::fast());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(data: &[u8]) -> Vec<u8> {
+    let mut compressed = vec![];
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+        encoder.write_all(data).unwrap();
+    }
+    compressed
+}
+
+#[test]
+fn identity_bodies_are_unchanged() {
+    assert_eq!(decompress(LIST.to_vec(), None).unwrap(), LIST);
+    assert_eq!(decompress(LIST.to_vec(), Some("identity")).unwrap(), LIST);
+}
+
+#[test]
+fn gzip_and_brotli_are_decompressed() {
+    assert_eq!(decompress(gzip(LIST), Some("gzip")).unwrap(), LIST);
+    assert_eq!(decompress(gzip(LIST), Some("X-GZIP")).unwrap(), LIST);
+    assert_eq!(decompress(brotli(LIST), Some("br")).unwrap(), LIST);
+}
+
+#[test]
+fn deflate_accepts_zlib_and_raw_streams() {
+    let mut zlib = ZlibEncoder::new(vec![], Compression::fast());
+    zlib.write_all(LIST).unwrap();
+    let mut raw = DeflateEncoder::new(vec![], Compression::fast());
+    raw.write_all(LIST).unwrap();
+
+    assert_eq!(
+        decompress(zlib.finish().unwrap(), Some("deflate")).unwrap(),
+        LIST
+    );
+    assert_eq!(
+        decompress(raw.finish().unwrap(), Some("deflate")).unwrap(),
+        LIST
+    );
+}
+
+#[test]
+fn codings_are_undone_from_last_to_first() {
+    let body = brotli(&gzip(LIST));
+
+    assert_eq!(decompress(body, Some("gzip, br")).unwrap(), LIST);
+}
+
+#[test]
+fn unknown_and_corrupted_codings_fail() {
+    let error = decompress(LIST.to_vec(), Some("compress")).unwrap_err();
+    assert!(error.to_string().contains("compress"), "got {}", error);
+
+    assert!(decompress(LIST.to_vec(), Some("gzip")).is_err());
+}
+
+#[test]
+fn oversized_bodies_fail() {
+    let bomb = gzip(&vec![0; MAX_DECOMPRESSED_SIZE + 1]);
+    let fitting = gzip(&vec![0; MAX_DECOMPRESSED_SIZE]);
+
+    let error = decompress(bomb, Some("gzip")).unwrap_err();
+    assert!(error.to_string().contains("exceeds"), "got {}", error);
+    assert_eq!(
+        decompress(fitting, Some("gzip")).unwrap().len(),
+        MAX_DECOMPRESSED_SIZE
+    );
+}
+
+#[test]
+fn declared_charsets_are_decoded() {
+    // "café" in ISO-8859-1
+    let latin1 = b"caf\xe9";
+
+    assert_eq!(
+        decode_charset(latin1, Some("text/plain; charset=ISO-8859-1")),
+        "café"
+    );
+    assert_eq!(
+        decode_charset(latin1, Some("text/html;CHARSET=\"latin1\"")),
+        "café"
+    );
+    // Unknown labels and missing charsets fall back to UTF-8
+    assert_eq!(
+        decode_charset(latin1, Some("text/plain; charset=unknown")),
+        "caf\u{fffd}"
+    );
+    assert_eq!(
+        decode_charset("café".as_bytes(), Some("text/plain")),
+        "café"
+    );
+    assert_eq!(decode_charset("café".as_bytes(), None), "café");
+}
+
+#[test]
+fn byte_order_marks_override_the_declared_charset() {
+    let utf8 = [&[0xef, 0xbb, 0xbf][..], "café".as_bytes()].concat();
+    let utf16 = [0xff, 0xfe, b'o', 0, b'k', 0];
+
+    assert_eq!(
+        decode_charset(&utf8, Some("text/plain; charset=ISO-8859-1")),
+        "café"
+    );
+    assert_eq!(decode_charset(&utf16, None), "ok");
+}
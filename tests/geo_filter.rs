@@ -0,0 +1,173 @@
+use fluxy::geolookup::{models::GeoData, GeoFilter, GeoRadius};
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(ToString::to_string).collect()
+}
+
+/// A proxy located in Paris.
+fn paris() -> GeoData {
+    GeoData {
+        continent_code: Some("EU".to_string()),
+        iso_code: Some("FR".to_string()),
+        region_iso_code: Some("IDF".to_string()),
+        city_name: Some("Paris".to_string()),
+        latitude: Some(48.8566),
+        longitude: Some(2.3522),
+        is_in_european_union: true,
+        asn: Some(3215),
+        ..Default::default()
+    }
+}
+
+/// A proxy located in Los Angeles.
+fn los_angeles() -> GeoData {
+    GeoData {
+        continent_code: Some("NA".to_string()),
+        iso_code: Some("US".to_string()),
+        region_iso_code: Some("CA".to_string()),
+        city_name: Some("Los Angeles".to_string()),
+        latitude: Some(34.0522),
+        longitude: Some(-118.2437),
+        asn: Some(16509),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn empty_filter_accepts_everything() {
+    let filter = GeoFilter::default();
+
+    assert!(filter.is_empty());
+    assert!(filter.matches(&paris()));
+    assert!(filter.matches(&GeoData::default()));
+}
+
+#[test]
+fn countries_are_compared_ignoring_case() {
+    let filter = GeoFilter {
+        countries: strings(&["fr", "DE"]),
+        ..Default::default()
+    };
+
+    assert!(!filter.is_empty());
+    assert!(filter.matches(&paris()));
+    assert!(!filter.matches(&los_angeles()));
+}
+
+#[test]
+fn unknown_locations_never_match_an_include_list() {
+    let include = GeoFilter {
+        continents: strings(&["EU"]),
+        ..Default::default()
+    };
+    let exclude = GeoFilter {
+        exclude_continents: strings(&["EU"]),
+        ..Default::default()
+    };
+
+    assert!(!include.matches(&GeoData::default()));
+    assert!(exclude.matches(&GeoData::default()));
+}
+
+#[test]
+fn excludes_win_over_includes() {
+    let filter = GeoFilter {
+        continents: strings(&["EU", "NA"]),
+        exclude_countries: strings(&["us"]),
+        ..Default::default()
+    };
+
+    assert!(filter.matches(&paris()));
+    assert!(!filter.matches(&los_angeles()));
+}
+
+#[test]
+fn regions_may_be_prefixed_by_the_country() {
+    let bare = GeoFilter {
+        regions: strings(&["ca"]),
+        ..Default::default()
+    };
+    let prefixed = GeoFilter {
+        regions: strings(&["US-CA"]),
+        ..Default::default()
+    };
+    let wrong_country = GeoFilter {
+        regions: strings(&["ES-CA"]),
+        ..Default::default()
+    };
+    let excluded = GeoFilter {
+        exclude_regions: strings(&["FR-IDF"]),
+        ..Default::default()
+    };
+
+    assert!(bare.matches(&los_angeles()));
+    assert!(prefixed.matches(&los_angeles()));
+    assert!(!wrong_country.matches(&los_angeles()));
+    assert!(!excluded.matches(&paris()));
+    assert!(excluded.matches(&los_angeles()));
+}
+
+#[test]
+fn cities_are_matched_by_name() {
+    let filter = GeoFilter {
+        exclude_cities: strings(&["los angeles"]),
+        ..Default::default()
+    };
+
+    assert!(filter.matches(&paris()));
+    assert!(!filter.matches(&los_angeles()));
+}
+
+#[test]
+fn asns_are_included_and_excluded() {
+    let include = GeoFilter {
+        asns: vec![3215],
+        ..Default::default()
+    };
+    let exclude = GeoFilter {
+        exclude_asns: vec![16509],
+        ..Default::default()
+    };
+
+    assert!(include.uses_asn());
+    assert!(include.matches(&paris()));
+    assert!(!include.matches(&los_angeles()));
+    assert!(!include.matches(&GeoData::default()));
+    assert!(exclude.uses_asn());
+    assert!(exclude.matches(&paris()));
+    assert!(!exclude.matches(&los_angeles()));
+    assert!(!GeoFilter::default().uses_asn());
+}
+
+#[test]
+fn area_keeps_nearby_proxies_with_coordinates() {
+    // Around London, about 340 km from Paris
+    let filter = GeoFilter {
+        within: Some(GeoRadius {
+            latitude: 51.5072,
+            longitude: -0.1276,
+            km: 500.0,
+        }),
+        ..Default::default()
+    };
+    let narrow = GeoFilter {
+        within: filter.within.map(|area| GeoRadius { km: 300.0, ..area }),
+        ..Default::default()
+    };
+
+    assert!(filter.matches(&paris()));
+    assert!(!narrow.matches(&paris()));
+    assert!(!filter.matches(&los_angeles()));
+    assert!(!filter.matches(&GeoData::default()));
+}
+
+#[test]
+fn eu_only_keeps_member_countries() {
+    let filter = GeoFilter {
+        eu_only: true,
+        ..Default::default()
+    };
+
+    assert!(filter.matches(&paris()));
+    assert!(!filter.matches(&los_angeles()));
+}
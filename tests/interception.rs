@@ -0,0 +1,159 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::{ok_response, proxy_at, read_request, JUDGE_BODY};
+use fluxy::{
+    proxy::models::{Protocol, Proxy},
+    validator::Config,
+};
+use tokio::{
+    io::{copy_bidirectional, AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_native_tls::TlsAcceptor;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Creates a TLS acceptor presenting the `<name>.pem` certificate of the fixtures.
+fn acceptor(name: &str) -> TlsAcceptor {
+    let certificate = std::fs::read(fixture(&format!("{}.pem", name))).unwrap();
+    let key = std::fs::read(fixture(&format!("{}.key", name))).unwrap();
+    let identity = native_tls::Identity::from_pkcs8(&certificate, &key).unwrap();
+    TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap())
+}
+
+/// Answers a judge request over TLS with the given certificate.
+async fn serve_judge<S: AsyncRead + AsyncWrite + Unpin>(stream: S, acceptor: &TlsAcceptor) {
+    // Handshakes rejected by the client end here
+    let Ok(mut stream) = acceptor.accept(stream).await else {
+        return;
+    };
+    if read_request(&mut stream).await.is_none() {
+        return;
+    }
+    let response = ok_response(JUDGE_BODY);
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+/// Starts an HTTPS judge presenting the `judge` certificate and returns its URL.
+async fn judge() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let acceptor = acceptor("judge");
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move { serve_judge(stream, &acceptor).await });
+        }
+    });
+    format!("https://localhost:{}/", port)
+}
+
+/// Starts an HTTPS proxy; an intercepting one answers in place of the judge, presenting the
+/// `mitm` certificate.
+async fn proxy(intercepting: bool) -> Proxy {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let acceptor = acceptor("mitm");
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let Some(request_line) = read_request(&mut stream).await else {
+                    return;
+                };
+                let target = request_line.split_whitespace().nth(1).unwrap().to_string();
+                if intercepting {
+                    stream
+                        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                        .await
+                        .unwrap();
+                    serve_judge(stream, &acceptor).await;
+                    return;
+                }
+                let Ok(mut upstream) = TcpStream::connect(target).await else {
+                    let _ = stream.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n").await;
+                    return;
+                };
+                stream
+                    .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                    .await
+                    .unwrap();
+                let _ = copy_bidirectional(&mut stream, &mut upstream).await;
+            });
+        }
+    });
+    proxy_at(address, vec![Protocol::Https])
+}
+
+/// Validates a proxy against the judge and returns the emitted result.
+async fn validate(proxy: Proxy, judge_url: String, pinned: bool, drop_intercepted: bool) -> Proxy {
+    let config = Config {
+        https_judge: Some(judge_url),
+        https_judge_certificate: pinned.then(|| fixture("judge.pem")),
+        drop_intercepted,
+        ..common::config(vec![Protocol::Https])
+    };
+    common::validate_one(proxy, config).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pinned_certificate_passes_through_a_tunnel() {
+    let result = validate(proxy(false).await, judge().await, true, false).await;
+
+    assert_eq!(result.proxy_type.unwrap().protocol, Protocol::Https);
+    assert_eq!(result.tls_intercepted, Some(false));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replaced_pinned_certificate_is_intercepted() {
+    let result = validate(proxy(true).await, judge().await, true, false).await;
+
+    assert!(result.proxy_type.is_some());
+    assert_eq!(result.tls_intercepted, Some(true));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn untrusted_judge_is_not_mistaken_for_interception() {
+    // The self-signed judge fails verification, but presents the same certificate directly
+    let result = validate(proxy(false).await, judge().await, false, true).await;
+
+    assert!(result.proxy_type.is_some());
+    assert_eq!(result.tls_intercepted, Some(false));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn certificate_differing_from_the_direct_one_is_intercepted() {
+    let result = validate(proxy(true).await, judge().await, false, false).await;
+
+    assert!(result.proxy_type.is_some());
+    assert_eq!(result.tls_intercepted, Some(true));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn intercepting_proxies_are_dropped() {
+    let result = validate(proxy(true).await, judge().await, false, true).await;
+
+    assert!(result.proxy_type.is_none());
+    assert_eq!(result.checks[0].error_kind, Some("intercepted"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn interception_is_unknown_without_a_direct_certificate() {
+    // Nothing listens on the judge's port, so only the intercepting proxy answers
+    let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let judge_url = format!("https://localhost:{}/", closed.local_addr().unwrap().port());
+    drop(closed);
+
+    let result = validate(proxy(true).await, judge_url, false, true).await;
+
+    assert!(result.proxy_type.is_some());
+    assert_eq!(result.tls_intercepted, None);
+}
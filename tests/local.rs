@@ -0,0 +1,153 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Arc,
+    time::Duration,
+};
+
+use fluxy::providers::{
+    models::{FetchOptions, Source},
+    IProxyTrait, LocalProvider,
+};
+use http_body_util::Empty;
+use hyper::body::Bytes;
+use hyper_tls::HttpsConnector;
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use url::Url;
+
+/// Creates a directory unique to the test, holding the given files.
+fn lists_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = env::temp_dir().join(format!("fluxy-local-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (file, content) in files {
+        fs::write(dir.join(file), content).unwrap();
+    }
+    // Nested directories are not read
+    fs::create_dir(dir.join("nested")).unwrap();
+    fs::write(dir.join("nested/ignored.txt"), "10.0.0.9:80").unwrap();
+    dir.canonicalize().unwrap()
+}
+
+fn sources(paths: Vec<PathBuf>) -> Vec<Source> {
+    LocalProvider::new(paths).sources().unwrap()
+}
+
+/// Returns the paths of the file sources.
+fn files(sources: &[Source]) -> Vec<PathBuf> {
+    sources
+        .iter()
+        .map(|source| source.url.to_file_path().unwrap())
+        .collect()
+}
+
+async fn read(source: &Source) -> String {
+    let client =
+        Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpsConnector::new());
+    let content = LocalProvider::new(vec![])
+        .fetch(
+            Arc::new(client),
+            source.url.as_str(),
+            Duration::from_secs(5),
+            &FetchOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert!(!content.from_cache);
+    assert_eq!(content.status, None);
+    content.body.into_owned()
+}
+
+fn file_url(path: &Path) -> Url {
+    Url::from_file_path(path).unwrap()
+}
+
+#[tokio::test]
+async fn files_are_read_as_sources() {
+    let dir = lists_dir("file", &[("list.txt", "10.0.0.1:8080\n")]);
+
+    let sources = sources(vec![dir.join("list.txt")]);
+
+    assert_eq!(files(&sources), [dir.join("list.txt")]);
+    assert_eq!(sources[0].url, file_url(&dir.join("list.txt")));
+    assert_eq!(sources[0].default_types.len(), 6);
+    assert_eq!(read(&sources[0]).await, "10.0.0.1:8080\n");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn directories_list_their_files_in_order() {
+    let dir = lists_dir("dir", &[("b.txt", ""), ("a.txt", ""), ("c.csv", "")]);
+
+    let sources = sources(vec![dir.clone()]);
+
+    assert_eq!(
+        files(&sources),
+        [dir.join("a.txt"), dir.join("b.txt"), dir.join("c.csv")]
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn glob_patterns_are_expanded_without_repeats() {
+    let dir = lists_dir("glob", &[("b.txt", ""), ("a.txt", ""), ("c.csv", "")]);
+    let pattern = dir.join("*.txt");
+
+    let sources = sources(vec![pattern, dir.join("a.txt")]);
+
+    assert_eq!(files(&sources), [dir.join("a.txt"), dir.join("b.txt")]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_paths_and_invalid_patterns_are_skipped() {
+    let dir = lists_dir("missing", &[("a.txt", "")]);
+
+    let sources = sources(vec![
+        dir.join("missing.txt"),
+        dir.join("[.txt"),
+        dir.join("a.txt"),
+    ]);
+
+    assert_eq!(files(&sources), [dir.join("a.txt")]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stdin_is_a_single_source() {
+    let sources = sources(vec![PathBuf::from("-"), PathBuf::from("-")]);
+
+    assert_eq!(sources.len(), 1);
+    assert_eq!(sources[0].url.as_str(), "stdin:");
+}
+
+#[test]
+fn stdin_is_read_by_the_cli() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_fluxy"))
+        .args(["--file", "-", "--no-cache", "sources", "--report"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"10.0.0.1:8080\n10.0.0.2:8080\n10.0.0.1:8080\n")
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let report = String::from_utf8_lossy(&output.stdout);
+    let row = report
+        .lines()
+        .find(|line| line.ends_with("stdin:"))
+        .unwrap_or_else(|| panic!("no stdin row in {}", report));
+    // Status, bytes, time, parsed and duplicates
+    let columns = row.split_whitespace().collect::<Vec<_>>();
+    assert_eq!(columns[1], "42");
+    assert_eq!(columns[3], "2");
+}
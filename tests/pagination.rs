@@ -0,0 +1,252 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use common::{ok_response, read_request};
+use fluxy::{
+    fetcher::{Config, ProxyFetcher, SourceStats},
+    providers::{
+        models::{Pagination, Source},
+        IProxyTrait,
+    },
+    proxy::models::Protocol,
+};
+use hashbrown::HashMap;
+use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+/// Pages requested from the listing site, in order.
+type Requested = Arc<Mutex<Vec<usize>>>;
+
+/// Starts a listing site serving the given body for `/?page=<n>`, 404 for other pages.
+async fn site(pages: HashMap<usize, &'static str>, requested: Requested) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let pages = pages.clone();
+            let requested = Arc::clone(&requested);
+            tokio::spawn(async move {
+                // Pages may be requested over a kept-alive connection
+                while let Some(request_line) = read_request(&mut stream).await {
+                    let page = request_line
+                        .split_whitespace()
+                        .nth(1)
+                        .and_then(|path| path.strip_prefix("/?page="))
+                        .and_then(|page| page.parse::<usize>().ok())
+                        .unwrap();
+                    requested.lock().unwrap().push(page);
+
+                    let response = match pages.get(&page) {
+                        Some(body) => ok_response(body),
+                        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                    };
+                    if stream.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    format!("http://{}/?page={{page}}", address)
+}
+
+/// A provider listing a single paginated source.
+struct PagedProvider {
+    pagination: Pagination,
+}
+
+impl IProxyTrait for PagedProvider {
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        let source = Source::new("http://unused.test/", vec![Protocol::Socks5])?;
+        Ok(vec![source.paginated(self.pagination.clone())?])
+    }
+}
+
+/// Fetches the paginated source and returns the proxy addresses and the source statistics.
+async fn fetch(pagination: Pagination) -> (Vec<String>, SourceStats) {
+    fetch_within(pagination, 5000).await
+}
+
+/// Fetches the paginated source, allowing `request_timeout` milliseconds for each request.
+async fn fetch_within(pagination: Pagination, request_timeout: u64) -> (Vec<String>, SourceStats) {
+    let config = Config {
+        request_timeout,
+        enable_geo_lookup: false,
+        use_cache: false,
+        use_providers: false,
+        providers: vec![Arc::new(PagedProvider { pagination })],
+        ..Default::default()
+    };
+    let fetcher = ProxyFetcher::gather(config).await.unwrap();
+    tokio::task::spawn_blocking(move || {
+        let mut fetcher = fetcher;
+        let proxies = fetcher
+            .by_ref()
+            .map(|proxy| proxy.as_text().to_string())
+            .collect::<Vec<_>>();
+        let mut stats = fetcher.stats();
+        assert_eq!(stats.len(), 1);
+        (proxies, stats.pop().unwrap())
+    })
+    .await
+    .unwrap()
+}
+
+fn pages(bodies: &[(usize, &'static str)]) -> HashMap<usize, &'static str> {
+    bodies.iter().copied().collect()
+}
+
+#[test]
+fn pagination_builds_the_page_urls() {
+    let range = Pagination::range("http://site.test/?page={page}", 2..=4);
+    assert_eq!(
+        range.urls(),
+        [
+            "http://site.test/?page=2",
+            "http://site.test/?page=3",
+            "http://site.test/?page=4"
+        ]
+    );
+
+    let open = Pagination::until_empty("http://site.test/list/{page}", 1);
+    let urls = open.urls();
+    assert_eq!(urls.len(), 100);
+    assert_eq!(urls[99], "http://site.test/list/100");
+
+    let source = Source::all("http://unused.test/")
+        .unwrap()
+        .paginated(range)
+        .unwrap();
+    assert_eq!(source.url.as_str(), "http://site.test/?page=2");
+    assert_eq!(source.urls().len(), 3);
+}
+
+#[test]
+fn invalid_source_urls_are_errors() {
+    let error = Source::all("not a url").err().expect("URL was accepted");
+    assert!(error.to_string().contains("not a url"), "got {}", error);
+
+    let source = Source::all("http://site.test/").unwrap();
+    let pagination = Pagination::range("/relative?page={page}", 1..=2);
+    assert!(source.paginated(pagination).is_err());
+}
+
+#[tokio::test]
+async fn invalid_sources_fail_the_gathering() {
+    let config = Config {
+        enable_geo_lookup: false,
+        use_cache: false,
+        use_providers: false,
+        providers: vec![Arc::new(PagedProvider {
+            pagination: Pagination::until_empty("site.test/{page}", 1),
+        })],
+        ..Default::default()
+    };
+
+    let error = ProxyFetcher::gather(config).await.err().unwrap();
+
+    assert!(error.to_string().contains("site.test/1"), "got {}", error);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_page_of_a_range_is_fetched() {
+    let requested = Requested::default();
+    let bodies = pages(&[
+        (1, "10.0.0.1:8080"),
+        (2, "10.0.0.2:8080\n10.0.0.3:8080"),
+        (3, "10.0.0.4:8080"),
+    ]);
+    let template = site(bodies, Arc::clone(&requested)).await;
+
+    let (proxies, stats) = fetch(Pagination::range(&template, 1..=3)).await;
+
+    assert_eq!(
+        proxies,
+        [
+            "10.0.0.1:8080",
+            "10.0.0.2:8080",
+            "10.0.0.3:8080",
+            "10.0.0.4:8080"
+        ]
+    );
+    assert_eq!(*requested.lock().unwrap(), [1, 2, 3]);
+    assert_eq!(stats.pages, 3);
+    assert_eq!(stats.parsed, 4);
+    assert!(stats.is_ok());
+    assert_eq!(stats.page_error, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn walk_stops_at_a_page_without_new_proxies() {
+    let requested = Requested::default();
+    // Past its last page, the site keeps serving the last one
+    let bodies = pages(&[
+        (1, "10.0.0.1:8080"),
+        (2, "10.0.0.2:8080"),
+        (3, "10.0.0.2:8080"),
+        (4, "10.0.0.3:8080"),
+    ]);
+    let template = site(bodies, Arc::clone(&requested)).await;
+
+    let (proxies, stats) = fetch(Pagination::until_empty(&template, 1)).await;
+
+    assert_eq!(proxies, ["10.0.0.1:8080", "10.0.0.2:8080"]);
+    assert_eq!(*requested.lock().unwrap(), [1, 2, 3]);
+    assert_eq!(stats.pages, 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn error_past_the_first_page_ends_the_walk() {
+    let requested = Requested::default();
+    let bodies = pages(&[(1, "10.0.0.1:8080"), (2, "10.0.0.2:8080")]);
+    let template = site(bodies, Arc::clone(&requested)).await;
+
+    let (proxies, stats) = fetch(Pagination::until_empty(&template, 1)).await;
+
+    assert_eq!(proxies.len(), 2);
+    assert_eq!(*requested.lock().unwrap(), [1, 2, 3]);
+    assert_eq!(stats.pages, 2);
+    assert!(stats.is_ok());
+    let page_error = stats.page_error.expect("page error was not recorded");
+    assert!(page_error.contains("page=3"), "recorded {}", page_error);
+    assert!(page_error.contains("404"), "recorded {}", page_error);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn error_on_the_first_page_fails_the_source() {
+    let requested = Requested::default();
+    let template = site(pages(&[]), Arc::clone(&requested)).await;
+
+    let (proxies, stats) = fetch(Pagination::until_empty(&template, 1)).await;
+
+    assert!(proxies.is_empty());
+    assert_eq!(*requested.lock().unwrap(), [1]);
+    assert_eq!(stats.pages, 0);
+    assert!(stats.error.is_some());
+    assert_eq!(stats.page_error, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn request_timeout_applies_to_every_page() {
+    // The site accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let template = format!("http://{}/?page={{page}}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut accepted = vec![];
+        loop {
+            accepted.push(listener.accept().await.unwrap());
+        }
+    });
+    let start_time = Instant::now();
+
+    let (proxies, stats) = fetch_within(Pagination::range(&template, 1..=2), 200).await;
+
+    assert!(start_time.elapsed() < Duration::from_secs(2));
+    assert!(proxies.is_empty());
+    assert!(stats.error.is_some());
+    assert!(stats.duration < 1.0, "took {}s", stats.duration);
+}
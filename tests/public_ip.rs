@@ -0,0 +1,234 @@
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use fluxy::resolver::{IpDetector, PublicIp, Strategy};
+use tokio::net::UdpSocket;
+
+const MAGIC_COOKIE: [u8; 4] = [0x21, 0x12, 0xA4, 0x42];
+const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const MAPPED_ADDRESS: u16 = 0x0001;
+
+fn public_ip(addresses: &[&str]) -> PublicIp {
+    let addresses = addresses
+        .iter()
+        .map(|address| address.parse().unwrap())
+        .collect::<Vec<IpAddr>>();
+    PublicIp::from_addresses(&addresses)
+}
+
+fn detector(strategies: Vec<Strategy>) -> IpDetector {
+    IpDetector::new(strategies).with_timeout(Duration::from_secs(2))
+}
+
+#[tokio::test]
+async fn fixed_addresses_are_used_as_is() {
+    let detected =
+        IpDetector::fixed(&["10.9.9.9".parse().unwrap(), "2001:db8::9".parse().unwrap()])
+            .detect()
+            .await
+            .unwrap();
+
+    assert_eq!(detected, public_ip(&["10.9.9.9", "2001:db8::9"]));
+}
+
+#[tokio::test]
+async fn env_addresses_are_parsed() {
+    env::set_var("FLUXY_TEST_ENV_ADDRESSES", " 10.9.9.9, 2001:db8::9\n");
+    let strategy = Strategy::Env("FLUXY_TEST_ENV_ADDRESSES".to_string());
+
+    let detected = detector(vec![strategy]).detect().await.unwrap();
+
+    assert_eq!(detected, public_ip(&["10.9.9.9", "2001:db8::9"]));
+}
+
+#[tokio::test]
+async fn invalid_or_missing_env_addresses_fail() {
+    env::set_var("FLUXY_TEST_ENV_INVALID", "10.9.9.9,localhost");
+    let strategies = vec![
+        Strategy::Env("FLUXY_TEST_ENV_INVALID".to_string()),
+        Strategy::Env("FLUXY_TEST_ENV_MISSING".to_string()),
+    ];
+
+    let error = detector(strategies).detect().await.unwrap_err();
+
+    assert_eq!(error.failures.len(), 2);
+    assert_eq!(error.failures[0].0, "env:FLUXY_TEST_ENV_INVALID");
+    assert!(error.failures[1].1.contains("is not set"));
+}
+
+#[tokio::test]
+async fn strategies_are_tried_until_both_families_are_known() {
+    let strategies = vec![
+        Strategy::Env("FLUXY_TEST_ENV_UNSET".to_string()),
+        Strategy::Fixed(public_ip(&["10.9.9.9"])),
+        Strategy::Fixed(public_ip(&["10.8.8.8"])),
+        Strategy::Fixed(public_ip(&["10.7.7.7", "2001:db8::9"])),
+        Strategy::Fixed(public_ip(&["2001:db8::8"])),
+    ];
+
+    let detected = detector(strategies).with_ipv6(true).detect().await.unwrap();
+
+    // The first address of each family wins
+    assert_eq!(detected, public_ip(&["10.9.9.9", "2001:db8::9"]));
+}
+
+#[tokio::test]
+async fn detection_stops_once_ipv4_is_known() {
+    // An unreachable server would fail only after the timeout
+    let strategies = vec![
+        Strategy::Fixed(public_ip(&["2001:db8::9"])),
+        Strategy::Fixed(public_ip(&["10.9.9.9"])),
+        Strategy::Stun("192.0.2.1:3478".to_string()),
+    ];
+    let start_time = Instant::now();
+
+    let detected = detector(strategies).detect().await.unwrap();
+
+    assert!(start_time.elapsed() < Duration::from_secs(1));
+    assert_eq!(detected, public_ip(&["10.9.9.9", "2001:db8::9"]));
+}
+
+#[tokio::test]
+async fn a_single_family_is_enough() {
+    let strategies = vec![
+        Strategy::Fixed(public_ip(&["10.9.9.9"])),
+        Strategy::Env("FLUXY_TEST_ENV_UNSET".to_string()),
+    ];
+
+    let detected = detector(strategies).with_ipv6(true).detect().await.unwrap();
+
+    assert_eq!(detected, public_ip(&["10.9.9.9"]));
+}
+
+/// Encodes an address attribute, XOR-ed with the magic cookie and transaction ID if `xor`.
+fn address_attribute(address: IpAddr, transaction_id: &[u8], xor: bool) -> Vec<u8> {
+    let (family, octets) = match address {
+        IpAddr::V4(v4) => (0x01, v4.octets().to_vec()),
+        IpAddr::V6(v6) => (0x02, v6.octets().to_vec()),
+    };
+    let mask = [&MAGIC_COOKIE[..], transaction_id].concat();
+    let octets = octets
+        .iter()
+        .zip(mask)
+        .map(|(octet, mask)| if xor { octet ^ mask } else { *octet })
+        .collect::<Vec<_>>();
+    let kind = if xor {
+        XOR_MAPPED_ADDRESS
+    } else {
+        MAPPED_ADDRESS
+    };
+
+    let mut attribute = kind.to_be_bytes().to_vec();
+    attribute.extend_from_slice(&(4 + octets.len() as u16).to_be_bytes());
+    attribute.extend_from_slice(&[0, family, 0, 0]);
+    attribute.extend_from_slice(&octets);
+    attribute
+}
+
+/// Starts a STUN server answering one binding request with the attributes built from its
+/// transaction ID, and returns its address.
+async fn stun_server<F>(attributes: F) -> SocketAddr
+where
+    F: FnOnce(&[u8]) -> Vec<u8> + Send + 'static,
+{
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut request = [0u8; 512];
+        let (len, client) = socket.recv_from(&mut request).await.unwrap();
+        assert_eq!(len, 20);
+        assert_eq!(&request[..2], [0x00, 0x01]);
+        let transaction_id = request[8..20].to_vec();
+
+        let attributes = attributes(&transaction_id);
+        let mut response = vec![0x01, 0x01];
+        response.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+        response.extend_from_slice(&MAGIC_COOKIE);
+        response.extend_from_slice(&transaction_id);
+        response.extend_from_slice(&attributes);
+        socket.send_to(&response, client).await.unwrap();
+    });
+    address
+}
+
+async fn detect_stun(server: SocketAddr) -> Result<PublicIp, fluxy::resolver::DetectError> {
+    detector(vec![Strategy::Stun(server.to_string())])
+        .detect()
+        .await
+}
+
+#[tokio::test]
+async fn stun_xor_mapped_address_is_decoded() {
+    let server = stun_server(|transaction_id| {
+        address_attribute("203.0.113.7".parse().unwrap(), transaction_id, true)
+    })
+    .await;
+
+    assert_eq!(
+        detect_stun(server).await.unwrap(),
+        public_ip(&["203.0.113.7"])
+    );
+}
+
+#[tokio::test]
+async fn stun_ipv6_address_is_decoded() {
+    let server = stun_server(|transaction_id| {
+        address_attribute("2001:db8::7".parse().unwrap(), transaction_id, true)
+    })
+    .await;
+
+    assert_eq!(
+        detect_stun(server).await.unwrap(),
+        public_ip(&["2001:db8::7"])
+    );
+}
+
+#[tokio::test]
+async fn stun_xor_mapped_address_is_preferred() {
+    let server = stun_server(|transaction_id| {
+        // An unknown attribute with padding comes first
+        let mut attributes = vec![0x80, 0x22, 0x00, 0x03, b'f', b'o', b'o', 0x00];
+        attributes.extend(address_attribute(
+            "198.51.100.1".parse().unwrap(),
+            transaction_id,
+            false,
+        ));
+        attributes.extend(address_attribute(
+            "203.0.113.7".parse().unwrap(),
+            transaction_id,
+            true,
+        ));
+        attributes
+    })
+    .await;
+
+    assert_eq!(
+        detect_stun(server).await.unwrap(),
+        public_ip(&["203.0.113.7"])
+    );
+}
+
+#[tokio::test]
+async fn stun_mapped_address_is_a_fallback() {
+    let server = stun_server(|transaction_id| {
+        address_attribute("198.51.100.1".parse().unwrap(), transaction_id, false)
+    })
+    .await;
+
+    assert_eq!(
+        detect_stun(server).await.unwrap(),
+        public_ip(&["198.51.100.1"])
+    );
+}
+
+#[tokio::test]
+async fn stun_response_without_address_fails() {
+    let server = stun_server(|_| vec![]).await;
+
+    let error = detect_stun(server).await.unwrap_err();
+
+    assert!(error.failures[0].1.contains("no mapped address"));
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use std::{
+    env, fs,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{ok_response, read_head};
+use fluxy::providers::{
+    cache::SourceCache,
+    models::{FetchOptions, Source, SourceContent},
+    IProxyTrait,
+};
+use hashbrown::HashMap;
+use http_body_util::Empty;
+use hyper::body::Bytes;
+use hyper_tls::HttpsConnector;
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+const LIST: &str = "10.0.0.1:8080";
+
+/// Requests received by the site, as `(path, conditional)` pairs in order of arrival.
+type Requested = Arc<Mutex<Vec<(String, bool)>>>;
+
+/// Starts a site answering each path with the given response, 404 for other paths.
+///
+/// `/list` answers `304 Not Modified` to conditional requests.
+async fn site(routes: HashMap<&'static str, String>, requested: Requested) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let routes = routes.clone();
+            let requested = Arc::clone(&requested);
+            tokio::spawn(async move {
+                while let Some(head) = read_head(&mut stream).await {
+                    let path = head.split_whitespace().nth(1).unwrap().to_string();
+                    let conditional = head.to_ascii_lowercase().contains("if-none-match:");
+                    requested.lock().unwrap().push((path.clone(), conditional));
+
+                    let response = match routes.get(path.as_str()) {
+                        Some(_) if conditional && path == "/list" => {
+                            "HTTP/1.1 304 Not Modified\r\n\r\n".to_string()
+                        }
+                        Some(response) => response.clone(),
+                        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                    };
+                    if stream.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    address
+}
+
+fn redirect(location: &str) -> String {
+    format!(
+        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n",
+        location
+    )
+}
+
+/// A provider relying on the default fetch.
+struct Site;
+
+impl IProxyTrait for Site {
+    fn sources(&self) -> anyhow::Result<Vec<Source>> {
+        Ok(vec![])
+    }
+}
+
+async fn fetch(url: &str, options: FetchOptions) -> anyhow::Result<SourceContent> {
+    let client =
+        Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpsConnector::new());
+    Site.fetch(Arc::new(client), url, Duration::from_secs(5), &options)
+        .await
+}
+
+fn paths(requested: &Requested) -> Vec<String> {
+    requested
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn relative_locations_are_joined() {
+    let requested = Requested::default();
+    let routes = HashMap::from([
+        ("/lists/start", redirect("next")),
+        ("/lists/next", redirect("/list")),
+        ("/list", ok_response(LIST)),
+    ]);
+    let address = site(routes, Arc::clone(&requested)).await;
+
+    let content = fetch(
+        &format!("http://{}/lists/start", address),
+        FetchOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(content.body, LIST);
+    assert_eq!(content.status, Some(200));
+    assert_eq!(content.url, format!("http://{}/list", address));
+    assert_eq!(paths(&requested), ["/lists/start", "/lists/next", "/list"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn redirect_loops_are_detected() {
+    let requested = Requested::default();
+    let routes = HashMap::from([("/a", redirect("/b")), ("/b", redirect("/a"))]);
+    let address = site(routes, Arc::clone(&requested)).await;
+
+    let error = fetch(&format!("http://{}/a", address), FetchOptions::default())
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("Redirect loop"), "got {}", error);
+    assert_eq!(paths(&requested), ["/a", "/b"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn redirects_are_followed_up_to_the_limit() {
+    let requested = Requested::default();
+    let routes = HashMap::from([
+        ("/1", redirect("/2")),
+        ("/2", redirect("/3")),
+        ("/3", redirect("/list")),
+        ("/list", ok_response(LIST)),
+    ]);
+    let address = site(routes, Arc::clone(&requested)).await;
+    let options = |max_redirects| FetchOptions {
+        max_redirects,
+        ..Default::default()
+    };
+
+    let error = fetch(&format!("http://{}/1", address), options(2))
+        .await
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("Too many redirects (limit is 2)"),
+        "got {}",
+        error
+    );
+    assert_eq!(paths(&requested), ["/1", "/2", "/3"]);
+
+    let content = fetch(&format!("http://{}/1", address), options(3))
+        .await
+        .unwrap();
+    assert_eq!(content.url, format!("http://{}/list", address));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_the_original_url_is_requested_conditionally() {
+    let requested = Requested::default();
+    let routes = HashMap::from([("/start", redirect("/list")), ("/list", ok_response(LIST))]);
+    let address = site(routes, Arc::clone(&requested)).await;
+    let url = format!("http://{}/start", address);
+    let dir = env::temp_dir().join(format!("fluxy-redirects-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let cache = SourceCache::new(dir.clone()).unwrap();
+    cache
+        .write(&url, &url, Some("\"v1\"".to_string()), None, "10.0.0.9:80")
+        .unwrap();
+    let options = FetchOptions {
+        cache: Some(Arc::new(cache)),
+        ..Default::default()
+    };
+
+    let content = fetch(&url, options).await.unwrap();
+
+    // The validators of the original URL say nothing about the redirect target
+    assert_eq!(content.body, LIST);
+    assert!(!content.from_cache);
+    assert_eq!(
+        *requested.lock().unwrap(),
+        [("/start".to_string(), true), ("/list".to_string(), false)]
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
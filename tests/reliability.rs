@@ -0,0 +1,173 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{ok_response, proxy_at, read_request, JUDGE_BODY};
+use fluxy::{
+    proxy::models::{Anonymity, CheckPhase, Protocol, Proxy},
+    validator::Config,
+};
+use tokio::{io::AsyncWriteExt, net::TcpListener, time::sleep};
+
+/// Answer of the fake proxy to one request.
+#[derive(Clone, Copy)]
+enum Answer {
+    Pass(Duration), // Judge response after a delay.
+    Fail,           // 502 response.
+}
+
+/// Requests received by the fake proxies, as `(port, index)` pairs in order of arrival.
+type Log = Arc<Mutex<Vec<(u16, usize)>>>;
+
+/// Starts an HTTP proxy giving the n-th answer to its n-th request, logging each request.
+async fn proxy(answers: Vec<Answer>, log: Log) -> Proxy {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let port = address.port();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let answers = answers.clone();
+            let log = Arc::clone(&log);
+            tokio::spawn(async move {
+                // Connection checks close without a request
+                if read_request(&mut stream).await.is_none() {
+                    return;
+                }
+                let index = {
+                    let mut log = log.lock().unwrap();
+                    let index = log.iter().filter(|(logged, _)| *logged == port).count();
+                    log.push((port, index));
+                    index
+                };
+                match answers[index] {
+                    Answer::Pass(delay) => {
+                        sleep(delay).await;
+                        let response = ok_response(JUDGE_BODY);
+                        let _ = stream.write_all(response.as_bytes()).await;
+                    }
+                    Answer::Fail => {
+                        let _ = stream
+                            .write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n")
+                            .await;
+                    }
+                }
+            });
+        }
+    });
+    proxy_at(address, vec![Protocol::Http(Anonymity::Unknown)])
+}
+
+/// Returns a configuration checking the HTTP protocol.
+fn config() -> Config {
+    common::config(vec![Protocol::Http(Anonymity::Unknown)])
+}
+
+fn requests(log: &Log) -> usize {
+    log.lock().unwrap().len()
+}
+
+const PASS: Answer = Answer::Pass(Duration::ZERO);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exact_share_of_successes_passes() {
+    let log = Log::default();
+    let mut answers = vec![PASS; 7];
+    answers.extend([Answer::Fail; 3]);
+    let config = Config {
+        reliability_checks: 10,
+        min_success_rate: 0.7,
+        ..config()
+    };
+
+    let results = common::validate(vec![proxy(answers, Arc::clone(&log)).await], config).await;
+
+    // 0.7 * 10 is slightly above 7, but 7 successes are enough
+    assert_eq!(requests(&log), 10);
+    assert!(results[0].proxy_type.is_some());
+    assert_eq!(results[0].success_rate, Some(0.7));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn checks_stop_once_the_minimum_is_out_of_reach() {
+    let log = Log::default();
+    let answers = vec![PASS, Answer::Fail, PASS, PASS, PASS];
+    let config = Config {
+        reliability_checks: 5,
+        min_success_rate: 1.0,
+        ..config()
+    };
+
+    let results = common::validate(vec![proxy(answers, Arc::clone(&log)).await], config).await;
+
+    assert_eq!(requests(&log), 2);
+    assert!(results[0].proxy_type.is_none());
+    assert_eq!(results[0].success_rate, Some(0.5));
+    let outcome = &results[0].checks[0];
+    assert_eq!(outcome.phase, CheckPhase::Reliability);
+    assert_eq!(outcome.error_kind, Some("unreliable"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn jitter_is_the_deviation_of_the_response_times() {
+    let log = Log::default();
+    let slow = Answer::Pass(Duration::from_millis(200));
+    let config = Config {
+        reliability_checks: 4,
+        ..config()
+    };
+
+    let results =
+        common::validate(vec![proxy(vec![PASS, slow, PASS, slow], log).await], config).await;
+
+    // Response times alternate between about 0 and 0.2 seconds
+    let jitter = results[0].jitter.expect("jitter was not measured");
+    assert!((jitter - 0.1).abs() < 0.05, "measured {}", jitter);
+    assert_eq!(results[0].success_rate, Some(1.0));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn jitter_needs_two_successes() {
+    let log = Log::default();
+    let config = Config {
+        reliability_checks: 2,
+        min_success_rate: 0.5,
+        ..config()
+    };
+
+    let results = common::validate(vec![proxy(vec![PASS, Answer::Fail], log).await], config).await;
+
+    assert!(results[0].proxy_type.is_some());
+    assert_eq!(results[0].jitter, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn workers_are_released_between_checks() {
+    let log = Log::default();
+    let first = proxy(vec![PASS; 2], Arc::clone(&log)).await;
+    let second = proxy(vec![PASS; 2], Arc::clone(&log)).await;
+    let (first_port, second_port) = (first.port, second.port);
+    let config = Config {
+        concurrency_limit: 1,
+        reliability_checks: 2,
+        reliability_interval: 500,
+        ..config()
+    };
+
+    let results = common::validate(vec![first, second], config).await;
+
+    // The second proxy is checked while the first one pauses
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            (first_port, 0),
+            (second_port, 0),
+            (first_port, 1),
+            (second_port, 1)
+        ]
+    );
+}
@@ -0,0 +1,160 @@
+use fluxy::{
+    geolookup::models::GeoData,
+    proxy::{
+        models::{Anonymity, Protocol, Proxy, ProxyType, Timings},
+        score::{rank, sort, ScoreWeights, SortKey},
+    },
+};
+
+const EPSILON: f64 = 1e-9;
+
+fn proxy(port: u16, protocol: Protocol, total: Option<f64>) -> Proxy {
+    Proxy {
+        ip: "10.0.0.1".parse().unwrap(),
+        port,
+        proxy_type: Some(ProxyType::checked(protocol)),
+        timings: Timings {
+            total,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn elite(port: u16, total: f64) -> Proxy {
+    proxy(port, Protocol::Http(Anonymity::Elite), Some(total))
+}
+
+fn ports(proxies: &[Proxy]) -> Vec<u16> {
+    proxies.iter().map(|proxy| proxy.port).collect()
+}
+
+#[test]
+fn weights_are_parsed_over_the_defaults() {
+    let weights = "latency=0.5, success-rate=0,bandwidth=1".parse::<ScoreWeights>();
+
+    assert_eq!(
+        weights,
+        Ok(ScoreWeights {
+            latency: 0.5,
+            success_rate: 0.0,
+            bandwidth: 1.0,
+            ..Default::default()
+        })
+    );
+    assert_eq!("".parse::<ScoreWeights>(), Ok(ScoreWeights::default()));
+}
+
+#[test]
+fn invalid_weights_are_rejected() {
+    for value in ["latency", "latency=fast", "latency=-1", "uptime=1"] {
+        assert!(
+            value.parse::<ScoreWeights>().is_err(),
+            "{} was accepted",
+            value
+        );
+    }
+}
+
+#[test]
+fn score_leaves_out_unmeasured_components() {
+    let weights = ScoreWeights::default();
+    // One second of latency scores 0.5, elite anonymity 1 and a single protocol 0.25
+    let expected = (weights.latency * 0.5 + weights.anonymity + weights.protocols * 0.25)
+        / (weights.latency + weights.anonymity + weights.protocols);
+
+    let score = weights.score(&elite(1, 1.0), 1);
+
+    assert!((score - expected).abs() < EPSILON, "scored {}", score);
+}
+
+#[test]
+fn score_uses_every_measured_component() {
+    let weights = ScoreWeights::default();
+    let mut proxy = proxy(1, Protocol::Http(Anonymity::Transparent), Some(0.0));
+    proxy.success_rate = Some(0.5);
+    proxy.bandwidth = Some(1_000_000.0);
+    let expected = (weights.latency + weights.success_rate * 0.5 + weights.bandwidth * 0.5)
+        + weights.protocols;
+
+    let score = weights.score(&proxy, 4);
+
+    assert!((score - expected).abs() < EPSILON, "scored {}", score);
+}
+
+#[test]
+fn score_without_weights_is_zero() {
+    let weights = ScoreWeights {
+        latency: 0.0,
+        anonymity: 0.0,
+        success_rate: 0.0,
+        bandwidth: 0.0,
+        protocols: 0.0,
+    };
+
+    assert_eq!(weights.score(&elite(1, 1.0), 1), 0.0);
+}
+
+#[test]
+fn rank_counts_protocols_per_address() {
+    let mut proxies = vec![
+        proxy(1, Protocol::Http(Anonymity::Elite), Some(1.0)),
+        proxy(1, Protocol::Http(Anonymity::Anonymous), Some(1.0)),
+        proxy(1, Protocol::Https, Some(1.0)),
+        proxy(2, Protocol::Http(Anonymity::Elite), Some(1.0)),
+    ];
+
+    rank(&mut proxies, &ScoreWeights::default());
+
+    // Both anonymity levels of HTTP count as one protocol
+    let weights = ScoreWeights {
+        latency: 0.0,
+        anonymity: 0.0,
+        ..Default::default()
+    };
+    assert!((weights.score(&proxies[0], 2) - 0.5).abs() < EPSILON);
+    assert!(proxies[2].score > proxies[3].score);
+    assert!(proxies.iter().all(|proxy| proxy.score.is_some()));
+}
+
+#[test]
+fn sort_by_score_puts_unscored_proxies_last() {
+    let mut proxies = vec![elite(1, 1.0), elite(2, 1.0), elite(3, 1.0)];
+    proxies[0].score = Some(0.2);
+    proxies[2].score = Some(0.9);
+
+    sort(&mut proxies, SortKey::Score);
+
+    assert_eq!(ports(&proxies), [3, 1, 2]);
+}
+
+#[test]
+fn sort_by_latency_puts_untimed_proxies_last() {
+    let mut proxies = vec![
+        elite(1, 2.0),
+        proxy(2, Protocol::Https, None),
+        elite(3, 0.5),
+    ];
+
+    sort(&mut proxies, SortKey::Latency);
+
+    assert_eq!(ports(&proxies), [3, 1, 2]);
+}
+
+#[test]
+fn sort_by_country_prefers_the_exit_location() {
+    let geo = |iso_code: &str| GeoData {
+        iso_code: Some(iso_code.to_string()),
+        ..Default::default()
+    };
+    let mut proxies = vec![elite(1, 1.0), elite(2, 2.0), elite(3, 1.0), elite(4, 0.5)];
+    proxies[0].geo = geo("US");
+    proxies[1].geo = geo("DE");
+    proxies[2].geo = geo("US");
+    proxies[2].exit_geo = Some(geo("DE"));
+
+    sort(&mut proxies, SortKey::Country);
+
+    // Same country sorts by latency; unknown countries come last
+    assert_eq!(ports(&proxies), [3, 2, 1, 4]);
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use std::time::Duration;
+
+use common::read_request;
+use fluxy::{validator::Reference, Error};
+use hyper::{header::HeaderValue, HeaderMap};
+use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+const URL: &str = "http://payload.test/";
+const BODY: &[u8] = b"<html><body>hello</body></html>";
+// SHA-256 of `BODY`
+const BODY_SHA256: &str = "85052df661cd7c51a9e04eff2a91ed8fc1aa833e95fa0dab4c7cec102cabcb31";
+
+fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.append(*name, HeaderValue::from_static(value));
+    }
+    headers
+}
+
+fn origin_headers() -> HeaderMap {
+    headers(&[
+        ("Content-Type", "text/html; charset=UTF-8"),
+        ("Content-Security-Policy", "script-src 'self'"),
+        ("Cache-Control", "max-age=600"),
+        ("Date", "Sun, 18 Oct 2026 21:00:00 GMT"),
+        ("Content-Length", "31"),
+    ])
+}
+
+#[test]
+fn untouched_payload_is_not_tampered() {
+    let reference = Reference::new(URL, &origin_headers(), BODY);
+    let tampering = reference.compare(&origin_headers(), BODY);
+
+    assert_eq!(tampering, Default::default());
+    assert!(!tampering.is_tampered());
+}
+
+#[test]
+fn proxy_and_cache_headers_are_ignored() {
+    let reference = Reference::new(URL, &origin_headers(), BODY);
+    let mut received = origin_headers();
+    received.insert(
+        "date",
+        HeaderValue::from_static("Sun, 18 Oct 2026 21:05:00 GMT"),
+    );
+    received.insert("via", HeaderValue::from_static("1.1 squid"));
+    received.insert("x-cache", HeaderValue::from_static("HIT from squid"));
+    received.insert("age", HeaderValue::from_static("300"));
+    received.insert("proxy-connection", HeaderValue::from_static("keep-alive"));
+    received.remove("content-length");
+    received.insert("transfer-encoding", HeaderValue::from_static("chunked"));
+
+    let tampering = reference.compare(&received, BODY);
+
+    assert_eq!(tampering, Default::default());
+}
+
+#[test]
+fn added_headers_are_reported_without_tampering() {
+    let reference = Reference::new(URL, &origin_headers(), BODY);
+    let mut received = origin_headers();
+    received.insert("X-Proxy-Id", HeaderValue::from_static("42"));
+
+    let tampering = reference.compare(&received, BODY);
+
+    assert_eq!(tampering.added_headers, ["x-proxy-id"]);
+    assert!(!tampering.is_tampered());
+}
+
+#[test]
+fn modified_body_is_tampered() {
+    let reference = Reference::new(URL, &origin_headers(), BODY);
+    let injected = b"<html><body>hello<script>ad()</script></body></html>";
+
+    let tampering = reference.compare(&origin_headers(), injected);
+
+    assert!(tampering.body_modified);
+    assert!(tampering.is_tampered());
+}
+
+#[test]
+fn removed_or_changed_content_headers_are_tampered() {
+    let reference = Reference::new(URL, &origin_headers(), BODY);
+    let mut received = origin_headers();
+    received.remove("content-security-policy");
+    received.insert("content-type", HeaderValue::from_static("text/plain"));
+
+    let tampering = reference.compare(&received, BODY);
+
+    assert_eq!(tampering.removed_headers, ["content-security-policy"]);
+    assert_eq!(tampering.changed_headers, ["content-type"]);
+    assert!(!tampering.body_modified);
+    assert!(tampering.is_tampered());
+}
+
+#[test]
+fn other_headers_may_be_removed() {
+    let reference = Reference::new(URL, &origin_headers(), BODY);
+    let mut received = origin_headers();
+    received.remove("cache-control");
+
+    assert!(!reference.compare(&received, BODY).is_tampered());
+}
+
+#[test]
+fn repeated_headers_are_compared_together() {
+    let origin = headers(&[("content-language", "en"), ("Content-Language", "fr")]);
+    let reference = Reference::new(URL, &origin, BODY);
+    let reordered = headers(&[("content-language", "fr"), ("content-language", "en")]);
+
+    assert!(!reference.compare(&origin, BODY).is_tampered());
+    assert_eq!(
+        reference.compare(&reordered, BODY).changed_headers,
+        ["content-language"]
+    );
+}
+
+/// Serves `BODY` once on a local listener and returns its URL.
+async fn serve_once() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read_request(&mut stream).await.unwrap();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n",
+            BODY.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(BODY).await.unwrap();
+    });
+    format!("http://{}/", address)
+}
+
+#[tokio::test]
+async fn fetch_accepts_the_expected_hash() {
+    let url = serve_once().await;
+    let reference = Reference::fetch(&url, Some(BODY_SHA256), Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    assert!(!reference
+        .compare(&headers(&[("content-type", "text/html")]), BODY)
+        .is_tampered());
+}
+
+#[tokio::test]
+async fn fetch_rejects_an_unexpected_hash() {
+    let url = serve_once().await;
+    let expected = "0".repeat(64);
+    let result = Reference::fetch(&url, Some(&expected), Duration::from_secs(5)).await;
+
+    assert!(matches!(result, Err(Error::Config(_))));
+}
@@ -0,0 +1,230 @@
+mod common;
+
+use std::{net::SocketAddr, time::Duration};
+
+use common::{ok_response, proxy_at, read_request, JUDGE_BODY};
+use fluxy::{
+    negotiators::{HttpNegotiator, HttpsNegotiator, NegotiatorTrait, Socks5Negotiator},
+    proxy::{
+        client::ProxyClient,
+        models::{Anonymity, Protocol, Proxy, Timings},
+    },
+};
+use http_body_util::Empty;
+use hyper::{body::Bytes, Request, Uri};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::sleep,
+};
+
+const DELAY: Duration = Duration::from_millis(200);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn proxy(address: SocketAddr) -> Proxy {
+    proxy_at(address, vec![])
+}
+
+fn request() -> Request<Empty<Bytes>> {
+    Request::get("http://judge.test/")
+        .body(Empty::<Bytes>::new())
+        .unwrap()
+}
+
+async fn listener() -> TcpListener {
+    TcpListener::bind("127.0.0.1:0").await.unwrap()
+}
+
+/// Answers an HTTP request after `DELAY`.
+async fn respond_late(stream: &mut TcpStream) {
+    read_request(stream)
+        .await
+        .expect("client closed the connection");
+    sleep(DELAY).await;
+    stream
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+        .await
+        .unwrap();
+}
+
+fn secs(value: Option<f64>) -> Duration {
+    Duration::from_secs_f64(value.expect("stage was not timed"))
+}
+
+// Relies on Linux dropping SYNs while the accept queue is full; other systems refuse them
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn connect_time_covers_the_handshake() {
+    use tokio::{net::TcpSocket, time::Instant};
+
+    // A listener whose accept queue is full drops new SYNs, so the client's connection
+    // is only established once the queue is drained and its SYN is retransmitted
+    let socket = TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let listener = socket.listen(1).unwrap();
+    let address = listener.local_addr().unwrap();
+    let mut pending = vec![];
+    for _ in 0..2 {
+        pending.push(TcpStream::connect(address).await.unwrap());
+    }
+    tokio::spawn(async move {
+        sleep(DELAY).await;
+        let mut accepted = vec![];
+        loop {
+            accepted.push(listener.accept().await.unwrap());
+        }
+    });
+
+    let start_time = Instant::now();
+    let tcp = proxy(address).connect_timeout(TIMEOUT).await.unwrap();
+    let elapsed = start_time.elapsed();
+
+    let tcp_connect = secs(tcp.timings.tcp_connect);
+    assert!(tcp_connect >= DELAY, "connect took {:?}", tcp_connect);
+    assert!(tcp_connect <= elapsed);
+    drop(pending);
+}
+
+#[tokio::test]
+async fn connect_time_is_not_counted_in_the_request() {
+    let listener = listener().await;
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        respond_late(&mut stream).await;
+    });
+
+    let response = proxy(address)
+        .send_request(request(), Some(HttpNegotiator), TIMEOUT)
+        .await
+        .unwrap();
+    let timings = response.timings;
+
+    assert!(secs(timings.tcp_connect) < DELAY);
+    assert_eq!(timings.negotiate, None);
+    assert_eq!(timings.tls_handshake, None);
+    assert!(secs(timings.ttfb) >= DELAY);
+    assert!(secs(timings.total) >= secs(timings.tcp_connect) + secs(timings.ttfb));
+}
+
+#[tokio::test]
+async fn socks5_negotiation_is_timed_as_round_trips() {
+    let listener = listener().await;
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut greeting = [0; 3];
+        stream.read_exact(&mut greeting).await.unwrap();
+        sleep(DELAY).await;
+        stream.write_all(&[5, 0]).await.unwrap();
+
+        let mut connect = [0; 10];
+        stream.read_exact(&mut connect).await.unwrap();
+        sleep(DELAY).await;
+        stream
+            .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+
+        respond_late(&mut stream).await;
+    });
+
+    let response = proxy(address)
+        .send_request(request(), Some(Socks5Negotiator), TIMEOUT)
+        .await
+        .unwrap();
+    let timings = response.timings;
+
+    assert!(secs(timings.tcp_connect) < DELAY);
+    let negotiate = secs(timings.negotiate);
+    assert!(negotiate >= DELAY * 2, "negotiation took {:?}", negotiate);
+    assert!(negotiate < DELAY * 3, "negotiation took {:?}", negotiate);
+    assert!(secs(timings.ttfb) >= DELAY);
+    assert!(secs(timings.total) >= negotiate + secs(timings.ttfb));
+}
+
+#[tokio::test]
+async fn https_negotiation_reads_short_replies() {
+    let listener = listener().await;
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read_request(&mut stream)
+            .await
+            .expect("client closed the connection");
+        sleep(DELAY).await;
+        stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+        // Keep the tunnel open, as a proxy waiting for the TLS handshake would
+        sleep(TIMEOUT).await;
+    });
+
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    let mut timings = Timings::default();
+    let uri = Uri::from_static("https://judge.test/");
+    tokio::time::timeout(
+        TIMEOUT,
+        HttpsNegotiator.negotiate(&mut stream, &mut timings, &address.to_string(), &uri),
+    )
+    .await
+    .expect("negotiation waited for more data than the reply")
+    .unwrap();
+
+    assert!(secs(timings.negotiate) >= DELAY);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn total_time_covers_reading_the_judge_body() {
+    let listener = listener().await;
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                // Connection checks close without a request
+                if read_request(&mut stream).await.is_none() {
+                    return;
+                }
+                let response = ok_response(JUDGE_BODY);
+                let (head, body) = response.split_at(response.len() - JUDGE_BODY.len());
+                stream.write_all(head.as_bytes()).await.unwrap();
+                sleep(DELAY).await;
+                stream.write_all(body.as_bytes()).await.unwrap();
+            });
+        }
+    });
+    let types = vec![Protocol::Http(Anonymity::Unknown)];
+
+    let result =
+        common::validate_one(proxy_at(address, types.clone()), common::config(types)).await;
+
+    let timings = result.timings;
+    assert!(secs(timings.ttfb) < DELAY);
+    assert!(
+        secs(timings.total) >= DELAY,
+        "total was {:?}",
+        timings.total
+    );
+}
+
+#[test]
+fn json_keeps_the_average_response_time() {
+    let proxy = Proxy {
+        timings: Timings {
+            ttfb: Some(0.5),
+            total: Some(1.5),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let json: serde_json::Value = serde_json::from_str(&proxy.as_json()).unwrap();
+
+    assert_eq!(json["average_response_time"], 1.5);
+    assert_eq!(json["timings"]["ttfb"], 0.5);
+    assert_eq!(json["timings"]["total"], 1.5);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&Proxy::default().as_json()).unwrap()
+            ["average_response_time"],
+        0.0
+    );
+}